@@ -0,0 +1,430 @@
+//! Disassembler for every W65C02S opcode, including the Rockwell bit
+//! instructions and `WAI`/`STP`.
+//!
+//! Memory is read through a caller-supplied peek function rather than a
+//! `System`, so disassembling never causes the side effects a real read
+//! might have.
+
+use core::fmt::{self, Display, Formatter, Write};
+use crate::op;
+
+/// How an instruction's operand bytes are interpreted.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum AddressingMode {
+    /// No operand, e.g. `CLC`
+    Implied,
+    /// `ASL A`
+    Accumulator,
+    /// `LDA #$12`
+    Immediate,
+    /// `LDA $12`
+    ZeroPage,
+    /// `LDA $12,X`
+    ZeroPageX,
+    /// `LDX $12,Y`
+    ZeroPageY,
+    /// `LDA ($12)`
+    ZeroPageIndirect,
+    /// `LDA ($12,X)`
+    ZeroPageXIndirect,
+    /// `LDA ($12),Y`
+    ZeroPageIndirectY,
+    /// `LDA $1234`
+    Absolute,
+    /// `LDA $1234,X`
+    AbsoluteX,
+    /// `LDA $1234,Y`
+    AbsoluteY,
+    /// `JMP ($1234)`
+    AbsoluteIndirect,
+    /// `JMP ($1234,X)`
+    AbsoluteXIndirect,
+    /// `BNE label`, a signed 8-bit displacement
+    Relative,
+    /// `BBR0 $12,label`, a zero page address followed by a displacement
+    ZeroPageRelative,
+}
+
+impl AddressingMode {
+    /// Number of bytes following the opcode.
+    pub fn operand_len(self) -> u16 {
+        use AddressingMode::*;
+        match self {
+            Implied | Accumulator => 0,
+            Immediate | ZeroPage | ZeroPageX | ZeroPageY | ZeroPageIndirect
+            | ZeroPageXIndirect | ZeroPageIndirectY | Relative => 1,
+            Absolute | AbsoluteX | AbsoluteY | AbsoluteIndirect
+            | AbsoluteXIndirect | ZeroPageRelative => 2,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+struct OpcodeInfo {
+    mnemonic: &'static str,
+    mode: AddressingMode,
+}
+
+const OPCODE_TABLE: [OpcodeInfo; 256] = {
+    use AddressingMode::*;
+    // the undefined opcodes are all NOPs, most of them single byte
+    let mut table = [OpcodeInfo { mnemonic: "NOP", mode: Implied }; 256];
+    let mut i = 0;
+    while i < 16 {
+        table[i << 4 | 0x2] = OpcodeInfo { mnemonic: "NOP", mode: Immediate };
+        i += 1;
+    }
+    table[0x54] = OpcodeInfo { mnemonic: "NOP", mode: ZeroPageX };
+    table[0xD4] = OpcodeInfo { mnemonic: "NOP", mode: ZeroPageX };
+    table[0x5C] = OpcodeInfo { mnemonic: "NOP", mode: Absolute };
+    table[0xDC] = OpcodeInfo { mnemonic: "NOP", mode: Absolute };
+
+    let mut i = 0;
+    while i < DEFINED.len() {
+        let (opcode, mnemonic, mode) = DEFINED[i];
+        table[opcode as usize] = OpcodeInfo { mnemonic, mode };
+        i += 1;
+    }
+    table
+};
+
+const DEFINED: &[(u8, &str, AddressingMode)] = {
+    use AddressingMode::*;
+    &[
+        (op::BRK, "BRK", Immediate),
+        (op::ORA_ZPXI, "ORA", ZeroPageXIndirect),
+        (op::TSB_ZP, "TSB", ZeroPage),
+        (op::ORA_ZP, "ORA", ZeroPage),
+        (op::ASL_ZP, "ASL", ZeroPage),
+        (op::RMB0_ZP, "RMB0", ZeroPage),
+        (op::PHP, "PHP", Implied),
+        (op::ORA_IMM, "ORA", Immediate),
+        (op::ASL_A, "ASL", Accumulator),
+        (op::TSB_ABS, "TSB", Absolute),
+        (op::ORA_ABS, "ORA", Absolute),
+        (op::ASL_ABS, "ASL", Absolute),
+        (op::BBR0, "BBR0", ZeroPageRelative),
+        (op::BPL, "BPL", Relative),
+        (op::ORA_ZPIY, "ORA", ZeroPageIndirectY),
+        (op::ORA_ZPI, "ORA", ZeroPageIndirect),
+        (op::TRB_ZP, "TRB", ZeroPage),
+        (op::ORA_ZPX, "ORA", ZeroPageX),
+        (op::ASL_ZPX, "ASL", ZeroPageX),
+        (op::RMB1_ZP, "RMB1", ZeroPage),
+        (op::CLC, "CLC", Implied),
+        (op::ORA_ABSY, "ORA", AbsoluteY),
+        (op::INC_A, "INC", Accumulator),
+        (op::TRB_ABS, "TRB", Absolute),
+        (op::ORA_ABSX, "ORA", AbsoluteX),
+        (op::ASL_ABSX, "ASL", AbsoluteX),
+        (op::BBR1, "BBR1", ZeroPageRelative),
+        (op::JSR, "JSR", Absolute),
+        (op::AND_ZPXI, "AND", ZeroPageXIndirect),
+        (op::BIT_ZP, "BIT", ZeroPage),
+        (op::AND_ZP, "AND", ZeroPage),
+        (op::ROL_ZP, "ROL", ZeroPage),
+        (op::RMB2_ZP, "RMB2", ZeroPage),
+        (op::PLP, "PLP", Implied),
+        (op::AND_IMM, "AND", Immediate),
+        (op::ROL_A, "ROL", Accumulator),
+        (op::BIT_ABS, "BIT", Absolute),
+        (op::AND_ABS, "AND", Absolute),
+        (op::ROL_ABS, "ROL", Absolute),
+        (op::BBR2, "BBR2", ZeroPageRelative),
+        (op::BMI, "BMI", Relative),
+        (op::AND_ZPIY, "AND", ZeroPageIndirectY),
+        (op::AND_ZPI, "AND", ZeroPageIndirect),
+        (op::BIT_ZPX, "BIT", ZeroPageX),
+        (op::AND_ZPX, "AND", ZeroPageX),
+        (op::ROL_ZPX, "ROL", ZeroPageX),
+        (op::RMB3_ZP, "RMB3", ZeroPage),
+        (op::SEC, "SEC", Implied),
+        (op::AND_ABSY, "AND", AbsoluteY),
+        (op::DEC_A, "DEC", Accumulator),
+        (op::BIT_ABSX, "BIT", AbsoluteX),
+        (op::AND_ABSX, "AND", AbsoluteX),
+        (op::ROL_ABSX, "ROL", AbsoluteX),
+        (op::BBR3, "BBR3", ZeroPageRelative),
+        (op::RTI, "RTI", Implied),
+        (op::EOR_ZPXI, "EOR", ZeroPageXIndirect),
+        (op::NOP_ZP, "NOP", ZeroPage),
+        (op::EOR_ZP, "EOR", ZeroPage),
+        (op::LSR_ZP, "LSR", ZeroPage),
+        (op::RMB4_ZP, "RMB4", ZeroPage),
+        (op::PHA, "PHA", Implied),
+        (op::EOR_IMM, "EOR", Immediate),
+        (op::LSR_A, "LSR", Accumulator),
+        (op::JMP_ABS, "JMP", Absolute),
+        (op::EOR_ABS, "EOR", Absolute),
+        (op::LSR_ABS, "LSR", Absolute),
+        (op::BBR4, "BBR4", ZeroPageRelative),
+        (op::BVC, "BVC", Relative),
+        (op::EOR_ZPIY, "EOR", ZeroPageIndirectY),
+        (op::EOR_ZPI, "EOR", ZeroPageIndirect),
+        (op::EOR_ZPX, "EOR", ZeroPageX),
+        (op::LSR_ZPX, "LSR", ZeroPageX),
+        (op::RMB5_ZP, "RMB5", ZeroPage),
+        (op::CLI, "CLI", Implied),
+        (op::EOR_ABSY, "EOR", AbsoluteY),
+        (op::PHY, "PHY", Implied),
+        (op::EOR_ABSX, "EOR", AbsoluteX),
+        (op::LSR_ABSX, "LSR", AbsoluteX),
+        (op::BBR5, "BBR5", ZeroPageRelative),
+        (op::RTS, "RTS", Implied),
+        (op::ADC_ZPXI, "ADC", ZeroPageXIndirect),
+        (op::STZ_ZP, "STZ", ZeroPage),
+        (op::ADC_ZP, "ADC", ZeroPage),
+        (op::ROR_ZP, "ROR", ZeroPage),
+        (op::RMB6_ZP, "RMB6", ZeroPage),
+        (op::PLA, "PLA", Implied),
+        (op::ADC_IMM, "ADC", Immediate),
+        (op::ROR_A, "ROR", Accumulator),
+        (op::JMP_ABSI, "JMP", AbsoluteIndirect),
+        (op::ADC_ABS, "ADC", Absolute),
+        (op::ROR_ABS, "ROR", Absolute),
+        (op::BBR6, "BBR6", ZeroPageRelative),
+        (op::BVS, "BVS", Relative),
+        (op::ADC_ZPIY, "ADC", ZeroPageIndirectY),
+        (op::ADC_ZPI, "ADC", ZeroPageIndirect),
+        (op::STZ_ZPX, "STZ", ZeroPageX),
+        (op::ADC_ZPX, "ADC", ZeroPageX),
+        (op::ROR_ZPX, "ROR", ZeroPageX),
+        (op::RMB7_ZP, "RMB7", ZeroPage),
+        (op::SEI, "SEI", Implied),
+        (op::ADC_ABSY, "ADC", AbsoluteY),
+        (op::PLY, "PLY", Implied),
+        (op::JMP_ABSXI, "JMP", AbsoluteXIndirect),
+        (op::ADC_ABSX, "ADC", AbsoluteX),
+        (op::ROR_ABSX, "ROR", AbsoluteX),
+        (op::BBR7, "BBR7", ZeroPageRelative),
+        (op::BRA, "BRA", Relative),
+        (op::STA_ZPXI, "STA", ZeroPageXIndirect),
+        (op::STY_ZP, "STY", ZeroPage),
+        (op::STA_ZP, "STA", ZeroPage),
+        (op::STX_ZP, "STX", ZeroPage),
+        (op::SMB0_ZP, "SMB0", ZeroPage),
+        (op::DEC_Y, "DEY", Implied),
+        (op::BIT_IMM, "BIT", Immediate),
+        (op::TXA, "TXA", Implied),
+        (op::STY_ABS, "STY", Absolute),
+        (op::STA_ABS, "STA", Absolute),
+        (op::STX_ABS, "STX", Absolute),
+        (op::BBS0, "BBS0", ZeroPageRelative),
+        (op::BCC, "BCC", Relative),
+        (op::STA_ZPIY, "STA", ZeroPageIndirectY),
+        (op::STA_ZPI, "STA", ZeroPageIndirect),
+        (op::STY_ZPX, "STY", ZeroPageX),
+        (op::STA_ZPX, "STA", ZeroPageX),
+        (op::STX_ZPY, "STX", ZeroPageY),
+        (op::SMB1_ZP, "SMB1", ZeroPage),
+        (op::TYA, "TYA", Implied),
+        (op::STA_ABSY, "STA", AbsoluteY),
+        (op::TXS, "TXS", Implied),
+        (op::STZ_ABS, "STZ", Absolute),
+        (op::STA_ABSX, "STA", AbsoluteX),
+        (op::STZ_ABSX, "STZ", AbsoluteX),
+        (op::BBS1, "BBS1", ZeroPageRelative),
+        (op::LDY_IMM, "LDY", Immediate),
+        (op::LDA_ZPXI, "LDA", ZeroPageXIndirect),
+        (op::LDX_IMM, "LDX", Immediate),
+        (op::LDY_ZP, "LDY", ZeroPage),
+        (op::LDA_ZP, "LDA", ZeroPage),
+        (op::LDX_ZP, "LDX", ZeroPage),
+        (op::SMB2_ZP, "SMB2", ZeroPage),
+        (op::TAY, "TAY", Implied),
+        (op::LDA_IMM, "LDA", Immediate),
+        (op::TAX, "TAX", Implied),
+        (op::LDY_ABS, "LDY", Absolute),
+        (op::LDA_ABS, "LDA", Absolute),
+        (op::LDX_ABS, "LDX", Absolute),
+        (op::BBS2, "BBS2", ZeroPageRelative),
+        (op::BCS, "BCS", Relative),
+        (op::LDA_ZPIY, "LDA", ZeroPageIndirectY),
+        (op::LDA_ZPI, "LDA", ZeroPageIndirect),
+        (op::LDY_ZPX, "LDY", ZeroPageX),
+        (op::LDA_ZPX, "LDA", ZeroPageX),
+        (op::LDX_ZPY, "LDX", ZeroPageY),
+        (op::SMB3_ZP, "SMB3", ZeroPage),
+        (op::CLV, "CLV", Implied),
+        (op::LDA_ABSY, "LDA", AbsoluteY),
+        (op::TSX, "TSX", Implied),
+        (op::LDY_ABSX, "LDY", AbsoluteX),
+        (op::LDA_ABSX, "LDA", AbsoluteX),
+        (op::LDX_ABSY, "LDX", AbsoluteY),
+        (op::BBS3, "BBS3", ZeroPageRelative),
+        (op::CPY_IMM, "CPY", Immediate),
+        (op::CMP_ZPXI, "CMP", ZeroPageXIndirect),
+        (op::CPY_ZP, "CPY", ZeroPage),
+        (op::CMP_ZP, "CMP", ZeroPage),
+        (op::DEC_ZP, "DEC", ZeroPage),
+        (op::SMB4_ZP, "SMB4", ZeroPage),
+        (op::INC_Y, "INY", Implied),
+        (op::CMP_IMM, "CMP", Immediate),
+        (op::DEC_X, "DEX", Implied),
+        (op::WAI, "WAI", Implied),
+        (op::CPY_ABS, "CPY", Absolute),
+        (op::CMP_ABS, "CMP", Absolute),
+        (op::DEC_ABS, "DEC", Absolute),
+        (op::BBS4, "BBS4", ZeroPageRelative),
+        (op::BNE, "BNE", Relative),
+        (op::CMP_ZPIY, "CMP", ZeroPageIndirectY),
+        (op::CMP_ZPI, "CMP", ZeroPageIndirect),
+        (op::CMP_ZPX, "CMP", ZeroPageX),
+        (op::DEC_ZPX, "DEC", ZeroPageX),
+        (op::SMB5_ZP, "SMB5", ZeroPage),
+        (op::CLD, "CLD", Implied),
+        (op::CMP_ABSY, "CMP", AbsoluteY),
+        (op::PHX, "PHX", Implied),
+        (op::STP, "STP", Implied),
+        (op::CMP_ABSX, "CMP", AbsoluteX),
+        (op::DEC_ABSX, "DEC", AbsoluteX),
+        (op::BBS5, "BBS5", ZeroPageRelative),
+        (op::CPX_IMM, "CPX", Immediate),
+        (op::SBC_ZPXI, "SBC", ZeroPageXIndirect),
+        (op::NOP_IMM, "NOP", Immediate),
+        (op::CPX_ZP, "CPX", ZeroPage),
+        (op::SBC_ZP, "SBC", ZeroPage),
+        (op::INC_ZP, "INC", ZeroPage),
+        (op::SMB6_ZP, "SMB6", ZeroPage),
+        (op::INC_X, "INX", Implied),
+        (op::SBC_IMM, "SBC", Immediate),
+        (op::NOP, "NOP", Implied),
+        (op::CPX_ABS, "CPX", Absolute),
+        (op::SBC_ABS, "SBC", Absolute),
+        (op::INC_ABS, "INC", Absolute),
+        (op::BBS6, "BBS6", ZeroPageRelative),
+        (op::BEQ, "BEQ", Relative),
+        (op::SBC_ZPIY, "SBC", ZeroPageIndirectY),
+        (op::SBC_ZPI, "SBC", ZeroPageIndirect),
+        (op::NOP_ZPX, "NOP", ZeroPageX),
+        (op::SBC_ZPX, "SBC", ZeroPageX),
+        (op::INC_ZPX, "INC", ZeroPageX),
+        (op::SMB7_ZP, "SMB7", ZeroPage),
+        (op::SED, "SED", Implied),
+        (op::SBC_ABSY, "SBC", AbsoluteY),
+        (op::PLX, "PLX", Implied),
+        (op::NOP_ABS, "NOP", Absolute),
+        (op::SBC_ABSX, "SBC", AbsoluteX),
+        (op::INC_ABSX, "INC", AbsoluteX),
+        (op::BBS7, "BBS7", ZeroPageRelative),
+    ]
+};
+
+/// A single decoded instruction.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Instruction {
+    /// Where the opcode was read from.
+    pub address: u16,
+    pub opcode: u8,
+    pub mnemonic: &'static str,
+    pub mode: AddressingMode,
+    /// The operand bytes, little endian. For `ZeroPageRelative` the low byte
+    /// is the zero page address and the high byte the displacement.
+    pub operand: u16,
+}
+
+impl Instruction {
+    /// Total length in bytes, including the opcode.
+    #[allow(clippy::len_without_is_empty)]
+    pub fn len(&self) -> u16 {
+        1 + self.mode.operand_len()
+    }
+
+    /// The address the operand refers to, if it can be known without
+    /// looking at registers or memory: the target of a branch, or the
+    /// address of a zero page or absolute operand (before indexing).
+    pub fn target(&self) -> Option<u16> {
+        use AddressingMode::*;
+        match self.mode {
+            Implied | Accumulator | Immediate => None,
+            Relative => Some(self.branch_target(self.operand as u8, 2)),
+            ZeroPageRelative => Some(self.branch_target((self.operand >> 8) as u8, 3)),
+            _ => Some(self.operand),
+        }
+    }
+
+    fn branch_target(&self, displacement: u8, len: u16) -> u16 {
+        self.address.wrapping_add(len).wrapping_add(displacement as i8 as u16)
+    }
+
+    /// Writes the instruction in ca65 syntax, replacing addresses with the
+    /// names `symbols` returns for them.
+    pub fn write_to<W: Write + ?Sized, S: Display>(&self, out: &mut W, symbols: impl Fn(u16) -> Option<S>) -> fmt::Result {
+        use AddressingMode::*;
+        let address = |out: &mut W, address: u16, zero_page: bool| -> fmt::Result {
+            match symbols(address) {
+                Some(name) => write!(out, "{}", name),
+                None if zero_page => write!(out, "${:02X}", address),
+                None => write!(out, "${:04X}", address),
+            }
+        };
+
+        write!(out, "{}", self.mnemonic)?;
+        let operand = self.operand;
+        match self.mode {
+            Implied => Ok(()),
+            Accumulator => write!(out, " A"),
+            Immediate => write!(out, " #${:02X}", operand),
+            ZeroPage => { out.write_char(' ')?; address(out, operand, true) }
+            ZeroPageX => { out.write_char(' ')?; address(out, operand, true)?; write!(out, ",X") }
+            ZeroPageY => { out.write_char(' ')?; address(out, operand, true)?; write!(out, ",Y") }
+            ZeroPageIndirect => { out.write_str(" (")?; address(out, operand, true)?; write!(out, ")") }
+            ZeroPageXIndirect => { out.write_str(" (")?; address(out, operand, true)?; write!(out, ",X)") }
+            ZeroPageIndirectY => { out.write_str(" (")?; address(out, operand, true)?; write!(out, "),Y") }
+            Absolute => { out.write_char(' ')?; address(out, operand, false) }
+            AbsoluteX => { out.write_char(' ')?; address(out, operand, false)?; write!(out, ",X") }
+            AbsoluteY => { out.write_char(' ')?; address(out, operand, false)?; write!(out, ",Y") }
+            AbsoluteIndirect => { out.write_str(" (")?; address(out, operand, false)?; write!(out, ")") }
+            AbsoluteXIndirect => { out.write_str(" (")?; address(out, operand, false)?; write!(out, ",X)") }
+            Relative => { out.write_char(' ')?; address(out, self.target().unwrap(), false) }
+            ZeroPageRelative => {
+                out.write_char(' ')?;
+                address(out, operand & 0xFF, true)?;
+                out.write_char(',')?;
+                address(out, self.target().unwrap(), false)
+            }
+        }
+    }
+}
+
+impl Display for Instruction {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        self.write_to(f, |_| None::<&str>)
+    }
+}
+
+/// Decodes the instruction at `address`, reading memory through `peek`.
+pub fn decode(address: u16, mut peek: impl FnMut(u16) -> u8) -> Instruction {
+    let opcode = peek(address);
+    let OpcodeInfo { mnemonic, mode } = OPCODE_TABLE[opcode as usize];
+    let operand = match mode.operand_len() {
+        0 => 0,
+        1 => peek(address.wrapping_add(1)) as u16,
+        _ => peek(address.wrapping_add(1)) as u16
+            | (peek(address.wrapping_add(2)) as u16) << 8,
+    };
+    Instruction { address, opcode, mnemonic, mode, operand }
+}
+
+/// Decodes consecutive instructions starting at `address`, forever (the
+/// address wraps around). Use `take` to limit it.
+pub struct Disassembler<F: FnMut(u16) -> u8> {
+    pub address: u16,
+    peek: F,
+}
+
+impl<F: FnMut(u16) -> u8> Disassembler<F> {
+    pub fn new(address: u16, peek: F) -> Self {
+        Disassembler { address, peek }
+    }
+}
+
+impl<F: FnMut(u16) -> u8> Iterator for Disassembler<F> {
+    type Item = Instruction;
+
+    fn next(&mut self) -> Option<Instruction> {
+        let instruction = decode(self.address, &mut self.peek);
+        self.address = self.address.wrapping_add(instruction.len());
+        Some(instruction)
+    }
+}
@@ -118,7 +118,17 @@
 //! > 3. This notice may not be removed or altered from any source
 //! > distribution.
 
+/// A bus cycle whose data is thrown away. Only performed with the
+/// `exact-cycles` feature, where every bus cycle is counted; otherwise the
+/// cycle is accounted for by `OPCODE_CYCLES` and skipped.
+macro_rules! spurious {
+    ($e:expr) => {
+        if cfg!(feature = "exact-cycles") { $e; }
+    };
+}
+
 pub mod op;
+pub mod disasm;
 mod addressing_modes;
 mod instructions;
 #[cfg(test)]
@@ -349,6 +359,57 @@ impl W65C02S {
     /// called during a `step`.
     #[inline(always)]
     pub fn get_state(&self) -> State { self.state }
+    /// Number of bytes produced by `snapshot`.
+    pub const SNAPSHOT_LEN: usize = 9;
+    /// Capture the entire runtime state of the processor, including the
+    /// interrupt lines and any pending interrupts, as a fixed-size byte
+    /// array. Use `from_snapshot` to get it back.
+    pub fn snapshot(&self) -> [u8; W65C02S::SNAPSHOT_LEN] {
+        let state = match self.state {
+            State::HasBeenReset => 0,
+            State::Running => 1,
+            State::AwaitingInterrupt => 2,
+            State::Stopped => 3,
+        };
+        let lines = (self.irq as u8)
+            | (self.irq_pending as u8) << 1
+            | (self.nmi as u8) << 2
+            | (self.nmi_edge as u8) << 3
+            | (self.nmi_pending as u8) << 4;
+        [
+            state,
+            self.pc as u8, (self.pc >> 8) as u8,
+            self.a, self.x, self.y, self.s, self.p,
+            lines,
+        ]
+    }
+    /// Recreate a processor from the output of `snapshot`. Returns `None` if
+    /// the bytes do not describe a valid state.
+    pub fn from_snapshot(bytes: &[u8; W65C02S::SNAPSHOT_LEN]) -> Option<W65C02S> {
+        let state = match bytes[0] {
+            0 => State::HasBeenReset,
+            1 => State::Running,
+            2 => State::AwaitingInterrupt,
+            3 => State::Stopped,
+            _ => return None,
+        };
+        let lines = bytes[8];
+        if lines & !0x1F != 0 { return None }
+        Some(W65C02S {
+            state,
+            pc: (bytes[2] as u16) << 8 | (bytes[1] as u16),
+            a: bytes[3],
+            x: bytes[4],
+            y: bytes[5],
+            s: bytes[6],
+            p: bytes[7] | P_1,
+            irq: lines & 0x01 != 0,
+            irq_pending: lines & 0x02 != 0,
+            nmi: lines & 0x04 != 0,
+            nmi_edge: lines & 0x08 != 0,
+            nmi_pending: lines & 0x10 != 0,
+        })
+    }
     /// Push a value onto the stack using the given `System`.
     #[inline(always)]
     pub fn push<S: System>(&mut self, system: &mut S, value: u8) {
@@ -358,7 +419,7 @@ impl W65C02S {
     /// Spurious push during reset.
     #[inline(always)]
     pub fn spurious_push<S: System>(&mut self, system: &mut S) {
-        // system.read_stack_spurious(self, 0x100 | self.s as u16);
+        spurious!(system.read_stack_spurious(self, 0x100 | self.s as u16));
         self.s = self.s.wrapping_sub(1);
     }
     /// Pop a value from the stack using the given `System`.
@@ -371,7 +432,7 @@ impl W65C02S {
     /// a JSR or RTS or most pulls.
     #[inline(always)]
     pub fn spurious_stack_read<S: System>(&mut self, system: &mut S) {
-        // system.read_spurious(self, 0x100 | (self.s as u16));
+        spurious!(system.read_spurious(self, 0x100 | (self.s as u16)));
     }
     /// Change the input on the `IRQB` pin. `false` means no interrupt pending.
     /// `true` means some interrupt is pending. Note that `IRQB` is an active-
@@ -392,6 +453,15 @@ impl W65C02S {
         self.nmi_edge = self.nmi_edge || (!self.nmi_edge && nmi);
         self.nmi = nmi;
     }
+    /// Whether the next `step` of a running processor will enter the NMI
+    /// handler instead of executing an instruction.
+    #[inline(always)]
+    pub fn get_nmi_pending(&self) -> bool { self.nmi_pending }
+    /// Whether the next `step` of a running processor will enter the IRQ
+    /// handler instead of executing an instruction. A pending NMI takes
+    /// priority over this.
+    #[inline(always)]
+    pub fn get_irq_pending(&self) -> bool { self.irq_pending }
     /// Internal function. Updates the IRQ and NMI edge flags.
     #[inline(always)]
     fn check_irq_edge(&mut self) {
@@ -417,27 +487,51 @@ impl W65C02S {
     }
     /// Step the processor once. This means executing an interrupt sequence,
     /// fetching an instruction, or doing a spurious read, depending on the
-    /// current state of the processor. Returns the new state.
+    /// current state of the processor. Returns the number of cycles taken.
+    ///
+    /// Always executes at least one bus cycle. May execute more.
+    ///
+    /// Without the `exact-cycles` feature, instructions skip their spurious
+    /// bus cycles and the count comes from `OPCODE_CYCLES`, which ignores
+    /// page-crossing, branch-taken and decimal mode penalties. With it, every
+    /// bus cycle is performed and the count is exact.
+    #[cfg(not(feature = "exact-cycles"))]
+    pub fn step<S: System>(&mut self, system: &mut S) -> i32 {
+        self.step_inner(system)
+    }
+    /// Step the processor once. This means executing an interrupt sequence,
+    /// fetching an instruction, or doing a spurious read, depending on the
+    /// current state of the processor. Returns the number of bus cycles
+    /// executed.
     ///
     /// Always executes at least one bus cycle. May execute more.
+    #[cfg(feature = "exact-cycles")]
     pub fn step<S: System>(&mut self, system: &mut S) -> i32 {
+        let mut counter = CycleCounter { system, cycles: 0 };
+        self.step_inner(&mut counter);
+        counter.cycles
+    }
+    fn step_inner<S: System>(&mut self, system: &mut S) -> i32 {
         match self.state {
-            State::Stopped => /*system.read_operand_spurious(self, self.pc),*/return 1,
+            State::Stopped => {
+                spurious!(system.read_operand_spurious(self, self.pc));
+                return 1
+            },
             State::AwaitingInterrupt => {
                 if self.irq || self.nmi_edge {
                     self.state = State::Running;
-                    // system.read_operand_spurious(self, self.pc);
+                    spurious!(system.read_operand_spurious(self, self.pc));
                 }
                 self.check_irq_edge();
-                // system.read_operand_spurious(self, self.pc);
+                spurious!(system.read_operand_spurious(self, self.pc));
 
                 return 2;
             },
             State::HasBeenReset => {
                 // first, we spuriously read an opcode
-                // system.read_opcode_spurious(self, self.pc);
+                spurious!(system.read_opcode_spurious(self, self.pc));
                 // second, we read ... the same byte, but with SYNC low
-                // system.read_operand_spurious(self, self.pc);
+                spurious!(system.read_operand_spurious(self, self.pc));
                 // three spurious pushes...
                 self.spurious_push(system);
                 self.spurious_push(system);
@@ -458,8 +552,8 @@ impl W65C02S {
                     self.nmi_pending = false;
                     self.nmi_edge = false;
                     let opcode_addr = self.get_pc();
-                    // system.read_opcode_spurious(self, opcode_addr);
-                    // system.read_spurious(self, opcode_addr);
+                    spurious!(system.read_opcode_spurious(self, opcode_addr));
+                    spurious!(system.read_spurious(self, opcode_addr));
                     self.push(system, (opcode_addr >> 8) as u8);
                     self.push(system, opcode_addr as u8);
                     self.push(system, self.p & !P_B);
@@ -472,8 +566,8 @@ impl W65C02S {
                 else if self.irq_pending {
                     self.irq_pending = false;
                     let opcode_addr = self.get_pc();
-                    // system.read_opcode_spurious(self, opcode_addr);
-                    // system.read_spurious(self, opcode_addr);
+                    spurious!(system.read_opcode_spurious(self, opcode_addr));
+                    spurious!(system.read_spurious(self, opcode_addr));
                     self.push(system, (opcode_addr >> 8) as u8);
                     self.push(system, opcode_addr as u8);
                     self.push(system, self.p);
@@ -755,3 +849,28 @@ impl W65C02S {
     }
 }
 
+/// Counts every bus cycle a step performs on the way to the real `System`.
+#[cfg(feature = "exact-cycles")]
+struct CycleCounter<'a, S: System> {
+    system: &'a mut S,
+    cycles: i32,
+}
+
+#[cfg(feature = "exact-cycles")]
+impl<S: System> System for CycleCounter<'_, S> {
+    fn read_opcode(&mut self, cpu: &mut W65C02S, addr: u16) -> u8 { self.cycles += 1; self.system.read_opcode(cpu, addr) }
+    fn read(&mut self, cpu: &mut W65C02S, addr: u16) -> u8 { self.cycles += 1; self.system.read(cpu, addr) }
+    fn read_locked(&mut self, cpu: &mut W65C02S, addr: u16) -> u8 { self.cycles += 1; self.system.read_locked(cpu, addr) }
+    fn read_locked_spurious(&mut self, cpu: &mut W65C02S, addr: u16) { self.cycles += 1; self.system.read_locked_spurious(cpu, addr) }
+    fn read_vector(&mut self, cpu: &mut W65C02S, addr: u16) -> u8 { self.cycles += 1; self.system.read_vector(cpu, addr) }
+    fn write(&mut self, cpu: &mut W65C02S, addr: u16, data: u8) { self.cycles += 1; self.system.write(cpu, addr, data) }
+    fn write_stack(&mut self, cpu: &mut W65C02S, addr: u16, data: u8) { self.cycles += 1; self.system.write_stack(cpu, addr, data) }
+    fn write_locked(&mut self, cpu: &mut W65C02S, addr: u16, data: u8) { self.cycles += 1; self.system.write_locked(cpu, addr, data) }
+    fn read_opcode_spurious(&mut self, cpu: &mut W65C02S, addr: u16) { self.cycles += 1; self.system.read_opcode_spurious(cpu, addr) }
+    fn read_operand(&mut self, cpu: &mut W65C02S, addr: u16) -> u8 { self.cycles += 1; self.system.read_operand(cpu, addr) }
+    fn read_operand_spurious(&mut self, cpu: &mut W65C02S, addr: u16) { self.cycles += 1; self.system.read_operand_spurious(cpu, addr) }
+    fn read_pointer(&mut self, cpu: &mut W65C02S, addr: u16) -> u8 { self.cycles += 1; self.system.read_pointer(cpu, addr) }
+    fn read_stack(&mut self, cpu: &mut W65C02S, addr: u16) -> u8 { self.cycles += 1; self.system.read_stack(cpu, addr) }
+    fn read_stack_spurious(&mut self, cpu: &mut W65C02S, addr: u16) { self.cycles += 1; self.system.read_stack_spurious(cpu, addr) }
+    fn read_spurious(&mut self, cpu: &mut W65C02S, addr: u16) { self.cycles += 1; self.system.read_spurious(cpu, addr) }
+}
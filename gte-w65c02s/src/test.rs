@@ -53,7 +53,8 @@ fn run_test(ram_image: &[(u16, Vec<u8>)], serial_in: Vec<u8>,
     let mut cpu = W65C02S::new();
     let mut stopped = false;
     for _ in 0..1000000 {
-        if cpu.step(&mut system) == State::Stopped {
+        cpu.step(&mut system);
+        if cpu.get_state() == State::Stopped {
             stopped = true;
             break
         }
@@ -120,3 +121,141 @@ fn pass_through_hello_world() {
         ]),
     ], b"Hello World!\n".to_vec(), b"Hello World!\n");
 }
+
+#[test]
+fn snapshot_round_trip() {
+    let mut system = SimpleSerialSystem::new(vec![], &[
+        (RESET_VECTOR, vec![0x00, 0x02]),
+        (0x0200, vec![
+            op::LDA_IMM, 0x42,
+            op::LDX_IMM, 0x13,
+            op::WAI,
+        ]),
+    ]);
+    let mut cpu = W65C02S::new();
+    for _ in 0..4 { cpu.step(&mut system); }
+    cpu.set_nmi(true);
+    let snapshot = cpu.snapshot();
+    let restored = W65C02S::from_snapshot(&snapshot).unwrap();
+    assert_eq!(restored, cpu);
+    assert_eq!(restored.get_state(), State::AwaitingInterrupt);
+    let mut bad = snapshot;
+    bad[0] = 0xFF;
+    assert!(W65C02S::from_snapshot(&bad).is_none());
+}
+
+#[test]
+fn disasm_lengths_match_execution() {
+    use disasm::decode;
+    // everything that doesn't just fall through to the next instruction
+    let control_flow = [op::BRK, op::JSR, op::RTI, op::RTS, op::JMP_ABS,
+                        op::JMP_ABSI, op::JMP_ABSXI, op::WAI, op::STP];
+    for opcode in 0..=255u8 {
+        if control_flow.contains(&opcode) { continue }
+        let mut system = SimpleSerialSystem::new(vec![], &[
+            (RESET_VECTOR, vec![0x00, 0x02]),
+            (0x0200, vec![opcode]),
+        ]);
+        let mut cpu = W65C02S::new();
+        cpu.step(&mut system); // reset
+        cpu.step(&mut system);
+        let instruction = decode(0x0200, |addr| system.ram[addr as usize]);
+        assert_eq!(cpu.get_pc(), 0x0200 + instruction.len(),
+                   "length of {:02X} ({})", opcode, instruction);
+    }
+}
+
+#[test]
+fn disasm_formatting() {
+    use disasm::{decode, Disassembler};
+    let mut ram = [0u8; 65536];
+    ram[0x8000..0x8010].copy_from_slice(&[
+        op::LDA_IMM, 0x12,
+        op::STA_ZPIY, 0x34,
+        op::JMP_ABSXI, 0x00, 0x90,
+        op::BNE, -6i8 as u8,
+        op::BBS3, 0x10, 0x02,
+        op::ASL_A,
+        op::WAI,
+        op::STP,
+        0x5C,
+    ]);
+    let peek = |addr: u16| ram[addr as usize];
+    let listing: Vec<String> = Disassembler::new(0x8000, peek).take(8)
+        .map(|i| i.to_string()).collect();
+    assert_eq!(listing, [
+        "LDA #$12", "STA ($34),Y", "JMP ($9000,X)", "BNE $8003",
+        "BBS3 $10,$800E", "ASL A", "WAI", "STP",
+    ]);
+
+    let mut out = String::new();
+    decode(0x8007, peek).write_to(&mut out, |addr| {
+        if addr == 0x8003 { Some("loop") } else { None }
+    }).unwrap();
+    assert_eq!(out, "BNE loop");
+    assert_eq!(decode(0x800F, peek).len(), 3);
+}
+
+#[test]
+fn pending_interrupts() {
+    let mut system = SimpleSerialSystem::new(vec![], &[
+        (RESET_VECTOR, vec![0x00, 0x02]),
+        (NMI_VECTOR, vec![0x00, 0x03]),
+        (IRQ_VECTOR, vec![0x00, 0x04]),
+        (0x0200, vec![op::CLI, op::NOP, op::NOP]),
+    ]);
+    let mut cpu = W65C02S::new();
+    cpu.step(&mut system); // reset
+    cpu.step(&mut system); // CLI
+    cpu.set_irq(true);
+    cpu.set_nmi(true);
+    cpu.step(&mut system); // NOP, which samples the interrupt lines
+    assert!(cpu.get_irq_pending());
+    assert!(cpu.get_nmi_pending());
+    cpu.step(&mut system); // the NMI wins
+    assert!(!cpu.get_nmi_pending());
+    assert!(cpu.get_irq_pending());
+    assert_eq!(cpu.get_pc(), 0x0300);
+}
+
+#[cfg(feature = "exact-cycles")]
+fn cycles_of(program: &[u8], setup: impl FnOnce(&mut W65C02S)) -> i32 {
+    let mut system = SimpleSerialSystem::new(vec![], &[
+        (RESET_VECTOR, vec![0x00, 0x02]),
+        (0x0200, program.to_vec()),
+    ]);
+    let mut cpu = W65C02S::new();
+    assert_eq!(cpu.step(&mut system), 7); // reset
+    setup(&mut cpu);
+    cpu.step(&mut system)
+}
+
+#[cfg(feature = "exact-cycles")]
+#[test]
+fn exact_cycle_penalties() {
+    // page crossing on an indexed read
+    assert_eq!(cycles_of(&[op::LDA_ABSX, 0x10, 0x10], |cpu| cpu.set_x(0x20)), 4);
+    assert_eq!(cycles_of(&[op::LDA_ABSX, 0x10, 0x10], |cpu| cpu.set_x(0xF0)), 5);
+    // indexed writes always pay it
+    assert_eq!(cycles_of(&[op::STA_ABSX, 0x10, 0x10], |cpu| cpu.set_x(0x20)), 5);
+    // branches: not taken, taken, taken across a page
+    assert_eq!(cycles_of(&[op::BNE, 0x10], |cpu| cpu.set_p(cpu.get_p() | P_Z)), 2);
+    assert_eq!(cycles_of(&[op::BNE, 0x10], |cpu| cpu.set_p(cpu.get_p() & !P_Z)), 3);
+    assert_eq!(cycles_of(&[op::BNE, -4i8 as u8], |cpu| cpu.set_p(cpu.get_p() & !P_Z)), 4);
+    // decimal mode arithmetic
+    assert_eq!(cycles_of(&[op::ADC_IMM, 0x01], |cpu| cpu.set_p(cpu.get_p() & !P_D)), 2);
+    assert_eq!(cycles_of(&[op::ADC_IMM, 0x01], |cpu| cpu.set_p(cpu.get_p() | P_D)), 3);
+    assert_eq!(cycles_of(&[op::SBC_ZP, 0x10], |cpu| cpu.set_p(cpu.get_p() | P_D)), 4);
+}
+
+#[cfg(feature = "exact-cycles")]
+#[test]
+fn exact_cycle_counts() {
+    assert_eq!(cycles_of(&[op::NOP], |_| {}), 2);
+    assert_eq!(cycles_of(&[op::JSR, 0x00, 0x03], |_| {}), 6);
+    assert_eq!(cycles_of(&[op::JMP_ABSI, 0x00, 0x03], |_| {}), 6);
+    assert_eq!(cycles_of(&[op::INC_ABSX, 0x00, 0x03], |_| {}), 7);
+    assert_eq!(cycles_of(&[op::TSB_ABS, 0x00, 0x03], |_| {}), 6);
+    assert_eq!(cycles_of(&[op::STA_ZPIY, 0x10], |_| {}), 6);
+    assert_eq!(cycles_of(&[0x5C, 0x00, 0x03], |_| {}), 8);
+}
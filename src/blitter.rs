@@ -1,7 +1,9 @@
 use log::{debug, info, warn};
 use crate::gametank_bus::{CpuBus};
+use crate::save_state::{Snapshot, StateError, StateReader, StateWriter};
+use crate::uninit::{UninitHit, UninitMemory, UninitReader};
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Blitter {
     // start_time: Instant,
 
@@ -27,6 +29,7 @@ pub struct Blitter {
 }
 
 impl Blitter {
+    #[allow(clippy::should_implement_trait)]
     pub fn default() -> Self {
         Self {
             src_y: 0,
@@ -53,7 +56,8 @@ impl Blitter {
         result
     }
 
-    pub fn cycle(&mut self, bus: &mut CpuBus) {
+    /// Advances the blitter by one cycle. Returns true if a blit finished on this cycle.
+    pub fn cycle(&mut self, bus: &mut CpuBus) -> bool {
         // debug!(target: "blitter", "{:?}", self);
 
         let (bit_start, start_addressed) = bus.blitter.start.read_once();
@@ -84,7 +88,7 @@ impl Blitter {
         }
 
         if !self.blitting {
-            return
+            return false
         }
 
         self.src_x = bus.blitter.gx;
@@ -106,7 +110,7 @@ impl Blitter {
             if bus.system_control.dma_flags.dma_irq() {
                 self.irq_trigger = true;
             }
-            return
+            return true
         }
 
 
@@ -116,7 +120,7 @@ impl Blitter {
         if !bus.system_control.dma_flags.dma_enable() {
             debug!(target: "blitter", "blit cycle skipped; dma access disabled. dma flags: {:08b}", bus.system_control.dma_flags.0);
             self.offset_x += 1;
-            return
+            return false
         }
 
         // get the next color to write
@@ -161,7 +165,13 @@ impl Blitter {
                 quad += 128*128;
             }
 
-            bus.vram_banks[vram_page][blit_src_x + blit_src_y*128 + quad]
+            let offset = blit_src_x + blit_src_y*128 + quad;
+            if let Some(hits) = &mut bus.uninit_hits {
+                if !bus.vram_written.get(vram_page * 256*256 + offset) {
+                    hits.push(UninitHit { reader: UninitReader::Blitter, address: offset as u16, memory: UninitMemory::Vram { page: vram_page as u8 }, offset });
+                }
+            }
+            bus.vram_banks[vram_page][offset]
         };
 
         let out_x = self.dst_x.wrapping_add(self.offset_x) as usize;
@@ -170,7 +180,7 @@ impl Blitter {
 
         if out_x >= 128 || out_y >= 128 {
             self.offset_x = self.offset_x.wrapping_add(1);
-            return
+            return false
         }
 
         // write to active framebuffer, if not transparent
@@ -180,6 +190,7 @@ impl Blitter {
 
         // increment x offset
         self.offset_x = self.offset_x.wrapping_add(1);
+        false
     }
 
     pub fn instant_blit(&mut self, bus: &mut CpuBus) {
@@ -195,3 +206,37 @@ impl Blitter {
         // }
     }
 }
+
+impl Snapshot for Blitter {
+    fn save(&self, out: &mut StateWriter) {
+        out.put_bytes(&[self.src_y, self.dst_y, self.height, self.src_x, self.dst_x, self.width, self.offset_x, self.offset_y, self.color]);
+        out.put_bool(self.flip_y);
+        out.put_bool(self.flip_x);
+        out.put_bool(self.color_fill);
+        out.put_bool(self.blitting);
+        out.put_i32(self.cycles);
+        out.put_bool(self.irq_trigger);
+    }
+
+    fn load(&mut self, input: &mut StateReader) -> Result<(), StateError> {
+        let [src_y, dst_y, height, src_x, dst_x, width, offset_x, offset_y, color] = input.get_array()?;
+        *self = Self {
+            src_y,
+            dst_y,
+            height,
+            flip_y: input.get_bool()?,
+            src_x,
+            dst_x,
+            width,
+            flip_x: input.get_bool()?,
+            offset_x,
+            offset_y,
+            color_fill: input.get_bool()?,
+            color,
+            blitting: input.get_bool()?,
+            cycles: input.get_i32()?,
+            irq_trigger: input.get_bool()?,
+        };
+        Ok(())
+    }
+}
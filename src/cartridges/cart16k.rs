@@ -25,6 +25,7 @@ impl DerefMut for Cartridge16K {
 }
 
 impl Cartridge for Cartridge16K {
+    #[allow(clippy::needless_borrow)]
     fn from_slice(slice: &[u8]) -> Self {
         let mut data = [0; 0x4000];
         data[0..0x4000].copy_from_slice(&slice);
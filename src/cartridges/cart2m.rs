@@ -1,50 +1,206 @@
-use core::mem::transmute;
-
 use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::vec;
+use alloc::vec::Vec;
 use crate::cartridges::Cartridge;
-
+use crate::cartridges::flash::{FlashChip, FlashOp, SECTOR_COUNT, SECTOR_SIZE};
+use crate::cartridges::storage::{SaveStorage, StorageError};
+use crate::save_state::{Snapshot, StateError, StateReader, StateWriter};
 
 #[derive(Debug, Clone)]
 pub struct Cartridge2M {
     data: Box<[[u8; 0x4000]; 128]>,
     pub bank_shifter: u8,
     pub bank_mask: u16,
+    pub flash: FlashChip,
+    // sectors that differ from the rom image, and their original contents
+    modified: u32,
+    originals: BTreeMap<u8, Vec<u8>>,
+    // sectors changed since they were last persisted
+    dirty: u32,
+}
+
+impl Cartridge2M {
+    pub fn image(&self) -> &[u8] {
+        self.data.as_flattened()
+    }
+
+    /// The bank mapped at `address`: the selected bank in the lower half, the last bank in the
+    /// upper half.
+    pub fn bank_at(&self, address: u16) -> u8 {
+        match address {
+            0x0000..=0x3FFF => { (self.bank_mask & 0x7F) as u8 }
+            _ => { 0x7F }
+        }
+    }
+
+    fn flash_address(&self, address: u16) -> u32 {
+        self.bank_at(address) as u32 * 0x4000 + (address as u32 & 0x3FFF)
+    }
+
+    /// A read by the cpu, which advances the flash chip's toggle bits while it's busy.
+    pub fn read(&mut self, address: u16) -> u8 {
+        let flash_address = self.flash_address(address);
+        match self.flash.read(flash_address) {
+            Some(status) => status,
+            None => self.image()[flash_address as usize],
+        }
+    }
+
+    pub fn tick(&mut self) {
+        self.flash.tick();
+    }
+
+    fn apply(&mut self, op: FlashOp) {
+        match op {
+            FlashOp::Program { address, data } => {
+                let address = address as usize;
+                let old = self.image()[address];
+                if old & data != old {
+                    self.touch(address / SECTOR_SIZE);
+                    self.data.as_flattened_mut()[address] = old & data;
+                }
+            }
+            FlashOp::Erase { sectors } => {
+                for sector in (0..SECTOR_COUNT).filter(|s| sectors & (1 << s) != 0) {
+                    self.touch(sector);
+                    self.sector_mut(sector).fill(0xFF);
+                }
+            }
+        }
+    }
+
+    /// Marks `sector` as about to change, keeping its rom contents the first time.
+    fn touch(&mut self, sector: usize) {
+        if self.modified & (1 << sector) == 0 {
+            self.originals.insert(sector as u8, self.sector(sector).to_vec());
+            self.modified |= 1 << sector;
+        }
+        self.dirty |= 1 << sector;
+    }
+
+    pub fn sector(&self, sector: usize) -> &[u8] {
+        &self.image()[sector * SECTOR_SIZE..(sector + 1) * SECTOR_SIZE]
+    }
+
+    fn sector_mut(&mut self, sector: usize) -> &mut [u8] {
+        &mut self.data.as_flattened_mut()[sector * SECTOR_SIZE..(sector + 1) * SECTOR_SIZE]
+    }
+
+    /// Sectors whose contents changed since `mark_clean`, or since the rom was loaded.
+    pub fn dirty_sectors(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..SECTOR_COUNT).filter(|s| self.dirty & (1 << s) != 0)
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        self.dirty != 0
+    }
+
+    /// Call once the dirty sectors have been persisted.
+    pub fn mark_clean(&mut self) {
+        self.dirty = 0;
+    }
+
+    /// Replaces sectors with the contents `storage` saved for them. Nothing is changed if any
+    /// sector fails to load.
+    pub fn load_save(&mut self, storage: &mut dyn SaveStorage) -> Result<(), StorageError> {
+        let mut loaded = Vec::new();
+        let mut buf = vec![0; SECTOR_SIZE];
+        for sector in 0..SECTOR_COUNT {
+            if storage.read_sector(sector as u32, &mut buf)? {
+                loaded.push((sector, buf.clone()));
+            }
+        }
+        for (sector, data) in loaded {
+            self.touch(sector);
+            self.sector_mut(sector).copy_from_slice(&data);
+        }
+        // what was just loaded is what's persisted
+        self.dirty = 0;
+        Ok(())
+    }
+
+    /// Writes the dirty sectors to `storage` and flushes it.
+    pub fn persist(&mut self, storage: &mut dyn SaveStorage) -> Result<(), StorageError> {
+        for sector in self.dirty_sectors() {
+            storage.write_sector(sector as u32, self.sector(sector))?;
+        }
+        storage.flush()?;
+        self.mark_clean();
+        Ok(())
+    }
+
+    /// Saves the flash state and the modified sectors, which a save state has to carry since
+    /// the rom image alone no longer describes them.
+    fn save_flash(&self, out: &mut StateWriter) {
+        self.flash.save(out);
+        out.put_u32(self.modified);
+        for sector in (0..SECTOR_COUNT).filter(|s| self.modified & (1 << s) != 0) {
+            out.put_bytes(self.sector(sector));
+        }
+    }
+
+    fn load_flash(&mut self, input: &mut StateReader) -> Result<(), StateError> {
+        self.flash.load(input)?;
+        let modified = input.get_u32()?;
+        for sector in 0..SECTOR_COUNT {
+            let bit = 1 << sector;
+            if modified & bit != 0 {
+                self.touch(sector);
+                input.read_into(self.sector_mut(sector))?;
+            } else if self.modified & bit != 0 {
+                let original = self.originals.remove(&(sector as u8)).expect("modified sectors keep their original");
+                self.sector_mut(sector).copy_from_slice(&original);
+                self.modified &= !bit;
+                self.dirty |= bit;
+            }
+        }
+        Ok(())
+    }
 }
 
 impl Cartridge for Cartridge2M {
     fn from_slice(slice: &[u8]) -> Self {
-        let mut data = [0u8; 0x4000*128];
-        data.copy_from_slice(&slice);
-        let data: Box<[[u8; 0x4000]; 128]> = unsafe { Box::new(transmute(data)) };
+        // built straight on the heap, 2M is more stack than most threads have
+        let mut data: Box<[[u8; 0x4000]; 128]> = vec![[0u8; 0x4000]; 128].into_boxed_slice().try_into().unwrap();
+        data.as_flattened_mut().copy_from_slice(slice);
         Self {
             data,
             bank_shifter: 0,
             bank_mask: 0x7E,
+            flash: FlashChip::default(),
+            modified: 0,
+            originals: BTreeMap::new(),
+            dirty: 0,
         }
     }
 
     fn read_byte(&self, address: u16) -> u8 {
-        match address {
-            0x4000..=0x7FFF => {
-                self.data[0x7F][address as usize & 0x3FFF]
-            }
-            0x0000..=0x3FFF => {
-                self.data[self.bank_mask as usize & 0x7F][address as usize & 0x3FFF]
-            }
-            _ => { panic!("how the hell did you get here?"); }
+        let flash_address = self.flash_address(address);
+        match self.flash.peek(flash_address) {
+            Some(status) => status,
+            None => self.image()[flash_address as usize],
         }
     }
 
-
     fn write_byte(&mut self, address: u16, data: u8) {
-        match address {
-            0x4000..=0x7FFF => {
-                self.data[0x7F][address as usize & 0x3FFF] = data;
-            }
-            0x0000..=0x3FFF => {
-                self.data[self.bank_mask as usize & 0x7F][address as usize & 0x3FFF] = data;
-            }
-            _ => { panic!("how the hell did you get here?"); }
+        let flash_address = self.flash_address(address);
+        if let Some(op) = self.flash.write(flash_address, data) {
+            self.apply(op);
         }
     }
 }
+
+impl Snapshot for Cartridge2M {
+    fn save(&self, out: &mut StateWriter) {
+        out.put_u8(self.bank_shifter);
+        out.put_u16(self.bank_mask);
+        self.save_flash(out);
+    }
+
+    fn load(&mut self, input: &mut StateReader) -> Result<(), StateError> {
+        self.bank_shifter = input.get_u8()?;
+        self.bank_mask = input.get_u16()?;
+        self.load_flash(input)
+    }
+}
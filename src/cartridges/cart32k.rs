@@ -22,6 +22,7 @@ impl DerefMut for Cartridge32K {
 }
 
 impl Cartridge for Cartridge32K {
+    #[allow(clippy::needless_borrow)]
     fn from_slice(slice: &[u8]) -> Self {
         let mut data = [0; 0x8000];
         data[0x0000..0x8000].copy_from_slice(&slice);
@@ -25,6 +25,7 @@ impl DerefMut for Cartridge8K {
 }
 
 impl Cartridge for Cartridge8K {
+    #[allow(clippy::needless_borrow)]
     fn from_slice(slice: &[u8]) -> Self {
         let mut data = [0; 0x2000];
         data[0..0x2000].copy_from_slice(&slice);
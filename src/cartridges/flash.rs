@@ -0,0 +1,346 @@
+use crate::save_state::{Snapshot, StateError, StateReader, StateWriter};
+
+/// The AM29F016 on 2M cartridges has 32 uniform sectors of 64K, four banks each.
+pub const SECTOR_SIZE: usize = 0x10000;
+pub const SECTOR_COUNT: usize = 32;
+
+const ALL_SECTORS: u32 = u32::MAX;
+
+// typical timings from the datasheet, in main cpu cycles
+const CPU_HZ: u32 = 3_579_545;
+const PROGRAM_CYCLES: u32 = 25; // 7us
+const SECTOR_ERASE_CYCLES: u32 = CPU_HZ; // 1s
+const CHIP_ERASE_CYCLES: u32 = 25 * CPU_HZ;
+// after a sector erase command, more sectors can be added for 50us before erasing starts
+const ERASE_WINDOW_CYCLES: u32 = 179;
+
+// unlock cycles only decode A0-A10
+const UNLOCK_MASK: u32 = 0x7FF;
+
+const MANUFACTURER_ID: u8 = 0x01;
+const DEVICE_ID: u8 = 0xAD;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Mode {
+    Read,
+    Unlock1,
+    Unlock2,
+    Autoselect,
+    ProgramSetup,
+    EraseSetup,
+    EraseUnlock1,
+    EraseUnlock2,
+    Programming { data: u8, cycles: u32 },
+    EraseWindow { sectors: u32, cycles: u32 },
+    Erasing { sectors: u32, cycles: u32 },
+}
+
+/// A change to the flash array a command asks for. The chip starts reporting its status as
+/// soon as the command is accepted, so the caller applies it right away.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FlashOp {
+    /// Clears the bits of the byte at `address` that are clear in `data`; programming can't
+    /// set bits.
+    Program { address: u32, data: u8 },
+    /// Sets every byte of the sectors in the mask to $FF.
+    Erase { sectors: u32 },
+}
+
+/// The command state machine of an AMD-style flash chip: unlock sequences, byte program,
+/// sector and chip erase, autoselect, and the data polling and toggle bits that report
+/// progress while the chip is busy. Reads return status instead of data from anywhere in the
+/// chip until the operation completes.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct FlashChip {
+    mode: Mode,
+    // DQ6 toggles on every status read, DQ2 on reads from a sector being erased
+    dq6: bool,
+    dq2: bool,
+}
+
+impl Default for FlashChip {
+    fn default() -> Self {
+        Self { mode: Mode::Read, dq6: false, dq2: false }
+    }
+}
+
+fn sector_bit(address: u32) -> u32 {
+    1 << ((address as usize / SECTOR_SIZE) % SECTOR_COUNT)
+}
+
+impl FlashChip {
+    /// Whether a program or erase operation is in progress.
+    pub fn is_busy(&self) -> bool {
+        matches!(self.mode, Mode::Programming { .. } | Mode::EraseWindow { .. } | Mode::Erasing { .. })
+    }
+
+    /// What a read of `address` returns, without toggling the status bits. `None` means the
+    /// array data is visible.
+    pub fn peek(&self, address: u32) -> Option<u8> {
+        let dq6 = (self.dq6 as u8) << 6;
+        let dq2 = |sectors: u32| if sectors & sector_bit(address) != 0 { (self.dq2 as u8) << 2 } else { 0 };
+        match self.mode {
+            Mode::Autoselect => Some(match address & 0xFF {
+                0x00 => MANUFACTURER_ID,
+                0x01 => DEVICE_ID,
+                _ => 0x00, // sector not protected
+            }),
+            // data polling: DQ7 reads inverted until the byte is programmed
+            Mode::Programming { data, .. } => Some((!data & 0x80) | dq6),
+            // DQ3 stays low while more sectors can still be added
+            Mode::EraseWindow { sectors, .. } => Some(dq6 | dq2(sectors)),
+            Mode::Erasing { sectors, .. } => Some(dq6 | 0x08 | dq2(sectors)),
+            _ => None,
+        }
+    }
+
+    /// A read of `address` by the cpu. Status reads toggle DQ6, and DQ2 within erasing sectors.
+    pub fn read(&mut self, address: u32) -> Option<u8> {
+        let value = self.peek(address);
+        match self.mode {
+            Mode::Programming { .. } => {
+                self.dq6 = !self.dq6;
+            }
+            Mode::EraseWindow { sectors, .. } | Mode::Erasing { sectors, .. } => {
+                self.dq6 = !self.dq6;
+                if sectors & sector_bit(address) != 0 {
+                    self.dq2 = !self.dq2;
+                }
+            }
+            _ => {}
+        }
+        value
+    }
+
+    /// A write of `value` to `address`, the offset into the whole chip.
+    pub fn write(&mut self, address: u32, value: u8) -> Option<FlashOp> {
+        let unlock = address & UNLOCK_MASK;
+        match self.mode {
+            // only erase suspend is accepted while busy, and that isn't supported
+            Mode::Programming { .. } | Mode::Erasing { .. } => {}
+            Mode::EraseWindow { sectors, .. } => {
+                if value == 0x30 {
+                    let sector = sector_bit(address);
+                    self.mode = Mode::EraseWindow { sectors: sectors | sector, cycles: ERASE_WINDOW_CYCLES };
+                    return Some(FlashOp::Erase { sectors: sector });
+                }
+            }
+            _ if value == 0xF0 => { self.mode = Mode::Read }
+            Mode::Read if unlock == 0x555 && value == 0xAA => { self.mode = Mode::Unlock1 }
+            Mode::Unlock1 if unlock == 0x2AA && value == 0x55 => { self.mode = Mode::Unlock2 }
+            Mode::Unlock2 if unlock == 0x555 => {
+                self.mode = match value {
+                    0xA0 => Mode::ProgramSetup,
+                    0x80 => Mode::EraseSetup,
+                    0x90 => Mode::Autoselect,
+                    _ => Mode::Read,
+                };
+            }
+            Mode::ProgramSetup => {
+                self.mode = Mode::Programming { data: value, cycles: PROGRAM_CYCLES };
+                return Some(FlashOp::Program { address, data: value });
+            }
+            Mode::EraseSetup if unlock == 0x555 && value == 0xAA => { self.mode = Mode::EraseUnlock1 }
+            Mode::EraseUnlock1 if unlock == 0x2AA && value == 0x55 => { self.mode = Mode::EraseUnlock2 }
+            Mode::EraseUnlock2 if unlock == 0x555 && value == 0x10 => {
+                self.mode = Mode::Erasing { sectors: ALL_SECTORS, cycles: CHIP_ERASE_CYCLES };
+                return Some(FlashOp::Erase { sectors: ALL_SECTORS });
+            }
+            Mode::EraseUnlock2 if value == 0x30 => {
+                let sector = sector_bit(address);
+                self.mode = Mode::EraseWindow { sectors: sector, cycles: ERASE_WINDOW_CYCLES };
+                return Some(FlashOp::Erase { sectors: sector });
+            }
+            // autoselect mode is only left with a reset command
+            Mode::Autoselect => {}
+            _ => { self.mode = Mode::Read }
+        }
+        None
+    }
+
+    /// Advances a program or erase operation by one main cpu cycle.
+    pub fn tick(&mut self) {
+        match &mut self.mode {
+            Mode::Programming { cycles, .. } | Mode::Erasing { cycles, .. } => {
+                *cycles -= 1;
+                if *cycles == 0 {
+                    self.mode = Mode::Read;
+                }
+            }
+            Mode::EraseWindow { sectors, cycles } => {
+                *cycles -= 1;
+                if *cycles == 0 {
+                    let sectors = *sectors;
+                    self.mode = Mode::Erasing { sectors, cycles: sectors.count_ones() * SECTOR_ERASE_CYCLES };
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Snapshot for FlashChip {
+    fn save(&self, out: &mut StateWriter) {
+        let (tag, data, sectors, cycles) = match self.mode {
+            Mode::Read => (0, 0, 0, 0),
+            Mode::Unlock1 => (1, 0, 0, 0),
+            Mode::Unlock2 => (2, 0, 0, 0),
+            Mode::Autoselect => (3, 0, 0, 0),
+            Mode::ProgramSetup => (4, 0, 0, 0),
+            Mode::EraseSetup => (5, 0, 0, 0),
+            Mode::EraseUnlock1 => (6, 0, 0, 0),
+            Mode::EraseUnlock2 => (7, 0, 0, 0),
+            Mode::Programming { data, cycles } => (8, data, 0, cycles),
+            Mode::EraseWindow { sectors, cycles } => (9, 0, sectors, cycles),
+            Mode::Erasing { sectors, cycles } => (10, 0, sectors, cycles),
+        };
+        out.put_u8(tag);
+        out.put_u8(data);
+        out.put_u32(sectors);
+        out.put_u32(cycles);
+        out.put_bool(self.dq6);
+        out.put_bool(self.dq2);
+    }
+
+    fn load(&mut self, input: &mut StateReader) -> Result<(), StateError> {
+        let tag = input.get_u8()?;
+        let data = input.get_u8()?;
+        let sectors = input.get_u32()?;
+        let cycles = input.get_u32()?;
+        if tag >= 8 && cycles == 0 {
+            return Err(StateError::Invalid("flash operation"));
+        }
+        self.mode = match tag {
+            0 => Mode::Read,
+            1 => Mode::Unlock1,
+            2 => Mode::Unlock2,
+            3 => Mode::Autoselect,
+            4 => Mode::ProgramSetup,
+            5 => Mode::EraseSetup,
+            6 => Mode::EraseUnlock1,
+            7 => Mode::EraseUnlock2,
+            8 => Mode::Programming { data, cycles },
+            9 => Mode::EraseWindow { sectors, cycles },
+            10 => Mode::Erasing { sectors, cycles },
+            _ => return Err(StateError::Invalid("flash mode")),
+        };
+        self.dq6 = input.get_bool()?;
+        self.dq2 = input.get_bool()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn command(chip: &mut FlashChip, command: u8) -> Option<FlashOp> {
+        chip.write(0x555, 0xAA);
+        chip.write(0x2AA, 0x55);
+        chip.write(0x555, command)
+    }
+
+    fn run(chip: &mut FlashChip, cycles: u32) {
+        for _ in 0..cycles {
+            chip.tick();
+        }
+    }
+
+    #[test]
+    fn byte_program() {
+        let mut chip = FlashChip::default();
+        assert_eq!(command(&mut chip, 0xA0), None);
+        assert_eq!(chip.write(0x12345, 0x5A), Some(FlashOp::Program { address: 0x12345, data: 0x5A }));
+        assert!(chip.is_busy());
+
+        // DQ7 reads as the complement of bit 7 of the data, DQ6 toggles on every read
+        let first = chip.read(0x12345).unwrap();
+        let second = chip.read(0x00000).unwrap();
+        assert_eq!(first & 0x80, 0x80);
+        assert_eq!(second & 0x80, 0x80);
+        assert_ne!(first & 0x40, second & 0x40);
+
+        // writes while programming are ignored
+        assert_eq!(chip.write(0x555, 0xAA), None);
+        run(&mut chip, PROGRAM_CYCLES - 1);
+        assert!(chip.is_busy());
+        chip.tick();
+        assert!(!chip.is_busy());
+        assert_eq!(chip.read(0x12345), None);
+    }
+
+    #[test]
+    fn broken_unlock_sequence_returns_to_read() {
+        let mut chip = FlashChip::default();
+        chip.write(0x555, 0xAA);
+        chip.write(0x2AB, 0x55);
+        assert_eq!(chip.write(0x555, 0xA0), None);
+        assert_eq!(chip.write(0x1000, 0x00), None);
+        assert!(!chip.is_busy());
+    }
+
+    #[test]
+    fn sector_erase_toggles_dq6_and_dq2() {
+        let mut chip = FlashChip::default();
+        command(&mut chip, 0x80);
+        chip.write(0x555, 0xAA);
+        chip.write(0x2AA, 0x55);
+        let sector = 3 * SECTOR_SIZE as u32;
+        assert_eq!(chip.write(sector + 0x42, 0x30), Some(FlashOp::Erase { sectors: 1 << 3 }));
+        assert!(chip.is_busy());
+
+        // DQ3 stays low while more sectors can be added
+        let a = chip.read(sector).unwrap();
+        let b = chip.read(sector + 0x100).unwrap();
+        assert_eq!(a & 0x08, 0);
+        assert_ne!(a & 0x40, b & 0x40);
+        assert_ne!(a & 0x04, b & 0x04);
+
+        // outside the erasing sector only DQ6 toggles, and DQ2 reads low
+        let c = chip.read(0).unwrap();
+        let d = chip.read(0).unwrap();
+        assert_ne!(c & 0x40, d & 0x40);
+        assert_eq!(c & 0x04, 0);
+        assert_eq!(d & 0x04, 0);
+
+        // another sector erase command inside the window adds to the erase
+        assert_eq!(chip.write(5 * SECTOR_SIZE as u32, 0x30), Some(FlashOp::Erase { sectors: 1 << 5 }));
+        run(&mut chip, ERASE_WINDOW_CYCLES);
+        let e = chip.read(5 * SECTOR_SIZE as u32).unwrap();
+        assert_eq!(e & 0x08, 0x08);
+
+        run(&mut chip, 2 * SECTOR_ERASE_CYCLES - 1);
+        assert!(chip.is_busy());
+        chip.tick();
+        assert!(!chip.is_busy());
+        assert_eq!(chip.read(sector), None);
+    }
+
+    #[test]
+    fn chip_erase() {
+        let mut chip = FlashChip::default();
+        command(&mut chip, 0x80);
+        chip.write(0x555, 0xAA);
+        chip.write(0x2AA, 0x55);
+        assert_eq!(chip.write(0x555, 0x10), Some(FlashOp::Erase { sectors: ALL_SECTORS }));
+        run(&mut chip, CHIP_ERASE_CYCLES);
+        assert!(!chip.is_busy());
+    }
+
+    #[test]
+    fn reset_during_autoselect() {
+        let mut chip = FlashChip::default();
+        command(&mut chip, 0x90);
+        assert_eq!(chip.read(0x00), Some(MANUFACTURER_ID));
+        assert_eq!(chip.read(0x01), Some(DEVICE_ID));
+        assert_eq!(chip.read(0x02), Some(0x00));
+
+        // only the reset command leaves autoselect
+        chip.write(0x555, 0xAA);
+        assert_eq!(chip.read(0x01), Some(DEVICE_ID));
+        chip.write(0x1234, 0xF0);
+        assert_eq!(chip.read(0x01), None);
+        assert_eq!(command(&mut chip, 0xA0), None);
+        assert!(chip.write(0x10, 0x00).is_some());
+    }
+}
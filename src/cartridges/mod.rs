@@ -4,13 +4,20 @@ pub mod cart8k;
 pub mod cart16k;
 pub mod cart32k;
 pub mod cart2m;
+pub mod flash;
+pub mod storage;
 
+use core::fmt::{Display, Formatter};
+
+use alloc::borrow::Cow;
 use alloc::boxed::Box;
 use log::error;
 use crate::cartridges::cart2m::Cartridge2M;
 use crate::cartridges::cart8k::Cartridge8K;
 use crate::cartridges::cart16k::Cartridge16K;
 use crate::cartridges::cart32k::{Cartridge32K};
+use crate::cartridges::storage::{SaveStorage, StorageError};
+use crate::save_state::{Snapshot, StateError, StateReader, StateWriter};
 
 pub trait Cartridge {
     fn from_slice(slice: &[u8]) -> Self;
@@ -22,36 +29,224 @@ pub trait Cartridge {
 
 #[derive(Debug, Clone)]
 pub enum CartridgeType {
+    /// Nothing in the slot. The bus reads it as open bus, as its `OpenBusTable` says.
+    Empty,
     Cart8k(Cartridge8K),
     Cart16k(Cartridge16K),
     Cart32k(Cartridge32K),
     Cart2m(Box<Cartridge2M>),
 }
 
+/// Why a rom image can't be put in a cartridge.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum RomError {
+    /// The image is empty, or larger than the biggest cartridge, or than the cartridge its
+    /// metadata names.
+    UnsupportedSize(usize),
+    /// The image isn't a power of two and doesn't end in padding, so it's most likely cut off.
+    /// `expected` is the size of the smallest cartridge it would fit.
+    Truncated { len: usize, expected: usize },
+    /// The reset vector points outside the cartridge's rom, so the cpu could never start it.
+    BadResetVector(u16),
+    /// The metadata header or sidecar couldn't be parsed. `line` is 0 when the header itself
+    /// is malformed.
+    BadMetadata { line: usize, reason: &'static str },
+}
+
+impl Display for RomError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            RomError::UnsupportedSize(len) => write!(f, "no cartridge holds a rom of {} bytes", len),
+            RomError::Truncated { len, expected } => write!(f, "rom is truncated: {} bytes, expected {}", len, expected),
+            RomError::BadResetVector(vector) => write!(f, "reset vector ${:04X} is outside the rom", vector),
+            RomError::BadMetadata { line: 0, reason } => write!(f, "bad rom header: {}", reason),
+            RomError::BadMetadata { line, reason } => write!(f, "bad rom metadata on line {}: {}", line, reason),
+        }
+    }
+}
+
+/// The kinds of cartridge, without their contents.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum CartridgeKind {
+    Cart8k,
+    Cart16k,
+    Cart32k,
+    Cart2m,
+}
+
+impl CartridgeKind {
+    pub const ALL: [CartridgeKind; 4] = [CartridgeKind::Cart8k, CartridgeKind::Cart16k, CartridgeKind::Cart32k, CartridgeKind::Cart2m];
+
+    pub fn rom_size(self) -> usize {
+        match self {
+            CartridgeKind::Cart8k => 0x2000,
+            CartridgeKind::Cart16k => 0x4000,
+            CartridgeKind::Cart32k => 0x8000,
+            CartridgeKind::Cart2m => 0x200000,
+        }
+    }
+
+    /// Whether the cartridge has flash the game can write to.
+    pub fn has_save(self) -> bool {
+        self == CartridgeKind::Cart2m
+    }
+}
+
+/// Brings an image to the size of the smallest of `kinds` that holds it. Trailing padding past
+/// the last power of two is dropped, and smaller power of two images are repeated to fill the
+/// cartridge, the way an undersized chip is mirrored across the address space.
+fn fit_rom_size<'a>(slice: &'a [u8], kinds: &[CartridgeKind]) -> Result<(CartridgeKind, Cow<'a, [u8]>), RomError> {
+    let fitting = |len: usize| kinds.iter().copied().find(|kind| kind.rom_size() >= len);
+    let mut image = slice;
+    if !image.len().is_power_of_two() {
+        let fitting = fitting(image.len()).map(CartridgeKind::rom_size);
+        let unpadded = match image.len().checked_ilog2() {
+            Some(log) => 1 << log,
+            None => return Err(RomError::UnsupportedSize(0)),
+        };
+        let padding = &image[unpadded..];
+        if padding.iter().all(|&b| b == padding[0]) && (padding[0] == 0x00 || padding[0] == 0xFF) {
+            image = &image[..unpadded];
+        } else {
+            return Err(match fitting {
+                Some(expected) => RomError::Truncated { len: image.len(), expected },
+                None => RomError::UnsupportedSize(image.len()),
+            });
+        }
+    }
+    match fitting(image.len()) {
+        Some(kind) if kind.rom_size() == image.len() => Ok((kind, Cow::Borrowed(image))),
+        Some(kind) => Ok((kind, Cow::Owned(image.repeat(kind.rom_size() / image.len())))),
+        None => Err(RomError::UnsupportedSize(slice.len())),
+    }
+}
+
 impl CartridgeType {
-    pub fn from_slice(slice: &[u8]) -> Self {
-        match slice.len() {
-            0x2000 => {
-                CartridgeType::Cart8k(Cartridge8K::from_slice(slice))
+    /// Puts a rom image in the cartridge type its size calls for, after fitting it to that
+    /// size with `fit_rom_size`.
+    pub fn from_slice(slice: &[u8]) -> Result<Self, RomError> {
+        Self::from_slice_as(slice, None)
+    }
+
+    /// Like `from_slice`, but puts the image in a `kind` of cartridge when one is given, as
+    /// when the rom's metadata names it.
+    pub fn from_slice_as(slice: &[u8], kind: Option<CartridgeKind>) -> Result<Self, RomError> {
+        let (kind, image) = match kind {
+            Some(kind) => fit_rom_size(slice, &[kind])?,
+            None => fit_rom_size(slice, &CartridgeKind::ALL)?,
+        };
+        let vector = u16::from_le_bytes([image[image.len() - 4], image[image.len() - 3]]);
+        // the lowest address the rom is visible at when the cpu comes out of reset
+        let rom_start = match kind {
+            CartridgeKind::Cart8k => 0xE000,
+            CartridgeKind::Cart2m => 0xC000,
+            _ => 0x8000,
+        };
+        if vector < rom_start {
+            return Err(RomError::BadResetVector(vector));
+        }
+        Ok(match kind {
+            CartridgeKind::Cart8k => {
+                CartridgeType::Cart8k(Cartridge8K::from_slice(&image))
             }
-            0x4000 => {
-                CartridgeType::Cart16k(Cartridge16K::from_slice(slice))
+            CartridgeKind::Cart16k => {
+                CartridgeType::Cart16k(Cartridge16K::from_slice(&image))
             }
-            0x8000 => {
-                CartridgeType::Cart32k(Cartridge32K::from_slice(slice))
+            CartridgeKind::Cart32k => {
+                CartridgeType::Cart32k(Cartridge32K::from_slice(&image))
             }
-            0x200000 => {
-                CartridgeType::Cart2m(Box::new(Cartridge2M::from_slice(slice)))
+            CartridgeKind::Cart2m => {
+                CartridgeType::Cart2m(Box::new(Cartridge2M::from_slice(&image)))
             }
-            _ => {
-                panic!("unimplemented");
+        })
+    }
+
+    /// The kind of cartridge in the slot, `None` if it's empty.
+    pub fn kind(&self) -> Option<CartridgeKind> {
+        match self {
+            CartridgeType::Empty => None,
+            CartridgeType::Cart8k(_) => Some(CartridgeKind::Cart8k),
+            CartridgeType::Cart16k(_) => Some(CartridgeKind::Cart16k),
+            CartridgeType::Cart32k(_) => Some(CartridgeKind::Cart32k),
+            CartridgeType::Cart2m(_) => Some(CartridgeKind::Cart2m),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        matches!(self, CartridgeType::Empty)
+    }
+
+    /// The raw rom image backing this cartridge.
+    pub fn image(&self) -> &[u8] {
+        match self {
+            CartridgeType::Empty => &[],
+            CartridgeType::Cart8k(c) => {c.as_slice()}
+            CartridgeType::Cart16k(c) => {c.as_slice()}
+            CartridgeType::Cart32k(c) => {c.as_slice()}
+            CartridgeType::Cart2m(c) => {c.image()}
+        }
+    }
+
+    /// The bank of the rom image mapped at `address`, for cartridges that have banks.
+    pub fn bank_at(&self, address: u16) -> Option<u8> {
+        match self {
+            CartridgeType::Cart2m(c) => { Some(c.bank_at(address)) }
+            _ => None,
+        }
+    }
+
+    /// Reads `address` as the cpu would. Unlike `read_byte`, this can have side effects, like
+    /// toggling a busy flash chip's status bits.
+    #[inline(always)]
+    pub fn read(&mut self, address: u16) -> u8 {
+        match self {
+            CartridgeType::Cart2m(c) => {c.read(address)}
+            _ => {self.read_byte(address)}
+        }
+    }
+
+    /// Advances anything on the cartridge that runs on its own, by one main cpu cycle.
+    #[inline(always)]
+    pub fn tick(&mut self) {
+        if let CartridgeType::Cart2m(c) = self {
+            if c.flash.is_busy() {
+                c.tick();
             }
         }
     }
 
+    /// Overwrites the cartridge's save region with what `storage` holds. Cartridges without
+    /// one ignore it.
+    pub fn load_save(&mut self, storage: &mut dyn SaveStorage) -> Result<(), StorageError> {
+        match self {
+            CartridgeType::Cart2m(c) => { c.load_save(storage) }
+            _ => Ok(()),
+        }
+    }
+
+    /// Whether the save region changed since it was last persisted, and is in a state worth
+    /// persisting: a flash chip in the middle of an operation isn't.
+    pub fn has_unsaved_changes(&self) -> bool {
+        match self {
+            CartridgeType::Cart2m(c) => { c.is_dirty() && !c.flash.is_busy() }
+            _ => false,
+        }
+    }
+
+    /// Writes what changed in the save region since the last call to `storage`.
+    pub fn persist(&mut self, storage: &mut dyn SaveStorage) -> Result<(), StorageError> {
+        match self {
+            CartridgeType::Cart2m(c) => { c.persist(storage) }
+            _ => Ok(()),
+        }
+    }
+
+    /// Reads `address` in the cartridge's rom. An empty slot reads as 0; the bus never asks
+    /// it, it answers with open bus itself.
     #[inline(always)]
     pub fn read_byte(&self, address: u16) -> u8 {
         match self {
+            CartridgeType::Empty => {0}
             CartridgeType::Cart8k(c) => {c.read_byte(address)}
             CartridgeType::Cart16k(c) => {c.read_byte(address)}
             CartridgeType::Cart32k(c) => {c.read_byte(address)}
@@ -62,7 +257,154 @@ impl CartridgeType {
     pub fn write_byte(&mut self, address: u16, data: u8) {
         match self {
             CartridgeType::Cart2m(c) => { c.write_byte(address, data) }
+            CartridgeType::Empty => {}
             _ => { error!("attempted write to non-writable cartridge") }
         }
     }
 }
+
+impl Snapshot for CartridgeType {
+    fn save(&self, out: &mut StateWriter) {
+        match self {
+            CartridgeType::Empty => { out.put_u8(4) }
+            CartridgeType::Cart8k(_) => { out.put_u8(0) }
+            CartridgeType::Cart16k(_) => { out.put_u8(1) }
+            CartridgeType::Cart32k(_) => { out.put_u8(2) }
+            CartridgeType::Cart2m(c) => {
+                out.put_u8(3);
+                c.save(out);
+            }
+        }
+    }
+
+    fn load(&mut self, input: &mut StateReader) -> Result<(), StateError> {
+        self.load_with(input, true)
+    }
+}
+
+impl CartridgeType {
+    /// Loads a `CART` chunk from a version 1 or 2 save state, from before flash contents were
+    /// saved. The flash keeps whatever it holds now.
+    pub(crate) fn load_v2(&mut self, input: &mut StateReader) -> Result<(), StateError> {
+        self.load_with(input, false)
+    }
+
+    fn load_with(&mut self, input: &mut StateReader, has_flash: bool) -> Result<(), StateError> {
+        match (input.get_u8()?, self) {
+            (0, CartridgeType::Cart8k(_)) => {}
+            (1, CartridgeType::Cart16k(_)) => {}
+            (2, CartridgeType::Cart32k(_)) => {}
+            (3, CartridgeType::Cart2m(c)) if has_flash => { c.load(input)?; }
+            (3, CartridgeType::Cart2m(c)) => {
+                c.bank_shifter = input.get_u8()?;
+                c.bank_mask = input.get_u16()?;
+            }
+            (4, CartridgeType::Empty) => {}
+            (0..=4, _) => { return Err(StateError::CartridgeMismatch) }
+            _ => { return Err(StateError::Invalid("cartridge type")) }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+
+    /// A `len` byte image with no padding in it, and the reset vector at its end.
+    fn rom(len: usize, reset: u16) -> Vec<u8> {
+        let mut rom: Vec<u8> = (0..len).map(|i| (i % 251) as u8 + 1).collect();
+        rom[len - 4..len - 2].copy_from_slice(&reset.to_le_bytes());
+        rom
+    }
+
+    fn load(image: &[u8], kind: Option<CartridgeKind>) -> Result<CartridgeType, RomError> {
+        CartridgeType::from_slice_as(image, kind)
+    }
+
+    #[test]
+    fn exact_sizes() {
+        for (len, reset, kind) in [
+            (0x2000, 0xE000, CartridgeKind::Cart8k),
+            (0x4000, 0x8000, CartridgeKind::Cart16k),
+            (0x8000, 0x8000, CartridgeKind::Cart32k),
+            (0x200000, 0xC000, CartridgeKind::Cart2m),
+        ] {
+            let image = rom(len, reset);
+            let cartridge = load(&image, None).unwrap();
+            assert_eq!(cartridge.kind(), Some(kind));
+            assert_eq!(cartridge.image(), &image[..]);
+        }
+    }
+
+    #[test]
+    fn small_images_are_mirrored() {
+        let image = rom(0x1000, 0xF000);
+        let cartridge = load(&image, None).unwrap();
+        assert_eq!(cartridge.kind(), Some(CartridgeKind::Cart8k));
+        assert_eq!(&cartridge.image()[..0x1000], &image[..]);
+        assert_eq!(&cartridge.image()[0x1000..], &image[..]);
+
+        // between the 32K and 2M sizes
+        let image = rom(0x10000, 0xC000);
+        let cartridge = load(&image, None).unwrap();
+        assert_eq!(cartridge.kind(), Some(CartridgeKind::Cart2m));
+        assert!(cartridge.image().chunks(0x10000).all(|chunk| chunk == image));
+    }
+
+    #[test]
+    fn padding_is_dropped() {
+        for pad in [0x00, 0xFF] {
+            let mut image = rom(0x2000, 0xE000);
+            image.resize(0x2000 + 0x200, pad);
+            let cartridge = load(&image, None).unwrap();
+            assert_eq!(cartridge.kind(), Some(CartridgeKind::Cart8k));
+            assert_eq!(cartridge.image(), &image[..0x2000]);
+        }
+    }
+
+    #[test]
+    fn truncated_images() {
+        let mut image = rom(0x2000, 0xE000);
+        image.extend_from_slice(&[0x01, 0x02, 0xFF]);
+        assert_eq!(load(&image, None).unwrap_err(), RomError::Truncated { len: 0x2003, expected: 0x4000 });
+
+        let image = rom(0x7000, 0x8000);
+        assert_eq!(load(&image, None).unwrap_err(), RomError::Truncated { len: 0x7000, expected: 0x8000 });
+
+        // too big to fit anything, so not truncated either
+        let image = rom(0x200000 + 0x1000, 0xC000);
+        assert_eq!(load(&image, None).unwrap_err(), RomError::UnsupportedSize(0x201000));
+    }
+
+    #[test]
+    fn unsupported_sizes() {
+        assert_eq!(load(&[], None).unwrap_err(), RomError::UnsupportedSize(0));
+        assert_eq!(load(&rom(0x400000, 0xC000), None).unwrap_err(), RomError::UnsupportedSize(0x400000));
+    }
+
+    #[test]
+    fn bad_reset_vectors() {
+        assert_eq!(load(&rom(0x2000, 0xDFFF), None).unwrap_err(), RomError::BadResetVector(0xDFFF));
+        assert_eq!(load(&rom(0x8000, 0x7FFF), None).unwrap_err(), RomError::BadResetVector(0x7FFF));
+        assert_eq!(load(&rom(0x200000, 0x8000), None).unwrap_err(), RomError::BadResetVector(0x8000));
+        assert!(load(&rom(0x2000, 0xE000), None).is_ok());
+    }
+
+    #[test]
+    fn forced_kinds() {
+        let image = rom(0x2000, 0xE000);
+        let cartridge = load(&image, Some(CartridgeKind::Cart32k)).unwrap();
+        assert_eq!(cartridge.kind(), Some(CartridgeKind::Cart32k));
+        assert!(cartridge.image().chunks(0x2000).all(|chunk| chunk == image));
+
+        let cartridge = load(&rom(0x4000, 0xC000), Some(CartridgeKind::Cart2m)).unwrap();
+        assert_eq!(cartridge.kind(), Some(CartridgeKind::Cart2m));
+
+        // the named kind has to hold the whole image
+        assert_eq!(load(&rom(0x8000, 0xE000), Some(CartridgeKind::Cart8k)).unwrap_err(), RomError::UnsupportedSize(0x8000));
+        // and its own reset vector rules apply
+        assert_eq!(load(&rom(0x4000, 0x8000), Some(CartridgeKind::Cart2m)).unwrap_err(), RomError::BadResetVector(0x8000));
+    }
+}
@@ -0,0 +1,164 @@
+use core::fmt::{Display, Formatter};
+
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// Where a cartridge keeps the contents of its battery or flash backed region between
+/// sessions. The region is addressed in sectors, whose size is up to the cartridge; a storage
+/// only has to hand back what was last written to each one.
+pub trait SaveStorage {
+    /// Fills `buf` with the saved contents of `sector`. Returns `false` and leaves `buf` alone
+    /// if the sector was never written.
+    fn read_sector(&mut self, sector: u32, buf: &mut [u8]) -> Result<bool, StorageError>;
+    fn write_sector(&mut self, sector: u32, data: &[u8]) -> Result<(), StorageError>;
+    /// Makes every write so far permanent. Writes may be lost until this returns.
+    fn flush(&mut self) -> Result<(), StorageError>;
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum StorageError {
+    /// The backing store failed, with the platform's description of why.
+    Io(String),
+    /// The saved data is malformed, or doesn't fit the cartridge it's loaded into.
+    Corrupt(&'static str),
+}
+
+impl Display for StorageError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            StorageError::Io(e) => write!(f, "save storage error: {}", e),
+            StorageError::Corrupt(what) => write!(f, "save data is corrupt: {}", what),
+        }
+    }
+}
+
+const MAGIC: &[u8; 4] = b"GTSV";
+
+/// Keeps sectors in memory. Frontends without a filesystem can persist it themselves with
+/// `to_bytes`, and restore it with `from_bytes`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MemoryStorage {
+    sectors: BTreeMap<u32, Vec<u8>>,
+}
+
+impl MemoryStorage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sectors.is_empty()
+    }
+
+    /// The `.sav` file format: a magic number, then for every written sector its number and
+    /// length as little endian u32s, followed by its contents.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::from(&MAGIC[..]);
+        for (sector, data) in &self.sectors {
+            out.extend_from_slice(&sector.to_le_bytes());
+            out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            out.extend_from_slice(data);
+        }
+        out
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, StorageError> {
+        let mut rest = bytes.strip_prefix(&MAGIC[..]).ok_or(StorageError::Corrupt("not a save file"))?;
+        let mut sectors = BTreeMap::new();
+        while !rest.is_empty() {
+            if rest.len() < 8 {
+                return Err(StorageError::Corrupt("truncated sector header"));
+            }
+            let sector = u32::from_le_bytes(rest[0..4].try_into().unwrap());
+            let len = u32::from_le_bytes(rest[4..8].try_into().unwrap()) as usize;
+            let data = rest[8..].get(..len).ok_or(StorageError::Corrupt("truncated sector"))?;
+            sectors.insert(sector, data.to_vec());
+            rest = &rest[8 + len..];
+        }
+        Ok(Self { sectors })
+    }
+}
+
+impl SaveStorage for MemoryStorage {
+    fn read_sector(&mut self, sector: u32, buf: &mut [u8]) -> Result<bool, StorageError> {
+        match self.sectors.get(&sector) {
+            Some(data) if data.len() == buf.len() => {
+                buf.copy_from_slice(data);
+                Ok(true)
+            }
+            Some(_) => Err(StorageError::Corrupt("sector size mismatch")),
+            None => Ok(false),
+        }
+    }
+
+    fn write_sector(&mut self, sector: u32, data: &[u8]) -> Result<(), StorageError> {
+        self.sectors.insert(sector, data.to_vec());
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), StorageError> {
+        Ok(())
+    }
+}
+
+#[cfg(feature = "std")]
+pub use file::FileStorage;
+
+#[cfg(feature = "std")]
+mod file {
+    use std::io::ErrorKind;
+    use std::path::PathBuf;
+    use std::string::ToString;
+    use super::{MemoryStorage, SaveStorage, StorageError};
+
+    fn io_error(e: std::io::Error) -> StorageError {
+        StorageError::Io(e.to_string())
+    }
+
+    /// A `.sav` file on disk. Sectors are kept in memory and the whole file is rewritten on
+    /// `flush`, through a temporary file so a crash never leaves it half written.
+    #[derive(Debug)]
+    pub struct FileStorage {
+        path: PathBuf,
+        sectors: MemoryStorage,
+        changed: bool,
+    }
+
+    impl FileStorage {
+        /// Opens the save file at `path`. A missing file is treated as an empty save, and is
+        /// only created once something is flushed.
+        pub fn open(path: impl Into<PathBuf>) -> Result<Self, StorageError> {
+            let path = path.into();
+            let sectors = match std::fs::read(&path) {
+                Ok(bytes) => MemoryStorage::from_bytes(&bytes)?,
+                Err(e) if e.kind() == ErrorKind::NotFound => MemoryStorage::new(),
+                Err(e) => return Err(io_error(e)),
+            };
+            Ok(Self { path, sectors, changed: false })
+        }
+    }
+
+    impl SaveStorage for FileStorage {
+        fn read_sector(&mut self, sector: u32, buf: &mut [u8]) -> Result<bool, StorageError> {
+            self.sectors.read_sector(sector, buf)
+        }
+
+        fn write_sector(&mut self, sector: u32, data: &[u8]) -> Result<(), StorageError> {
+            self.changed = true;
+            self.sectors.write_sector(sector, data)
+        }
+
+        fn flush(&mut self) -> Result<(), StorageError> {
+            if !self.changed {
+                return Ok(());
+            }
+            let mut temp = self.path.clone().into_os_string();
+            temp.push(".tmp");
+            std::fs::write(&temp, self.sectors.to_bytes()).map_err(io_error)?;
+            std::fs::rename(&temp, &self.path).map_err(io_error)?;
+            self.changed = false;
+            Ok(())
+        }
+    }
+}
@@ -1,4 +1,13 @@
-pub static COLOR_MAP_WRONG: [(u8, u8, u8, u8); 256] = [
+use alloc::boxed::Box;
+use crate::emulator::{HEIGHT, WIDTH};
+
+/// RGBA colors for each of the 256 values a framebuffer byte can hold.
+pub type ColorTable = [(u8, u8, u8, u8); 256];
+
+/// A whole frame of RGBA pixels, four bytes for each framebuffer byte.
+pub type RgbaFrame = [u8; (WIDTH * HEIGHT * 4) as usize];
+
+pub static COLOR_MAP_WRONG: ColorTable = [
     (0x00, 0x00, 0x00, 0xFF), (0x1F, 0x1F, 0x1F, 0xFF), (0x3F, 0x3F, 0x3F, 0xFF), (0x5F, 0x5F, 0x5F, 0xFF), (0x7F, 0x7F, 0x7F, 0xFF), (0x9F, 0x9F, 0x9F, 0xFF), (0xBF, 0xBF, 0xBF, 0xFF), (0xDF, 0xDF, 0xDF, 0xFF),
     (0x00, 0x00, 0x00, 0xFF), (0x17, 0x27, 0x17, 0xFF), (0x2F, 0x4F, 0x2F, 0xFF), (0x47, 0x77, 0x47, 0xFF), (0x5F, 0x9F, 0x5F, 0xFF), (0x87, 0xB7, 0x87, 0xFF), (0xAF, 0xCF, 0xAF, 0xFF), (0xD7, 0xE7, 0xD7, 0xFF),
     (0x00, 0x00, 0x00, 0xFF), (0x0F, 0x2F, 0x0F, 0xFF), (0x1F, 0x5F, 0x1F, 0xFF), (0x2F, 0x8F, 0x2F, 0xFF), (0x3F, 0xBF, 0x3F, 0xFF), (0x6F, 0xCF, 0x6F, 0xFF), (0x9F, 0xDF, 0x9F, 0xFF), (0xCF, 0xEF, 0xCF, 0xFF),
@@ -40,7 +49,7 @@ pub static COLOR_MAP_WRONG: [(u8, u8, u8, u8); 256] = [
     (0x00, 0x00, 0x00, 0xFF), (0x07, 0x37, 0x2B, 0xFF), (0x0F, 0x6F, 0x57, 0xFF), (0x17, 0xA7, 0x83, 0xFF), (0x1F, 0xDF, 0xAF, 0xFF), (0x57, 0xE7, 0xC3, 0xFF), (0x8F, 0xEF, 0xD7, 0xFF), (0xC7, 0xF7, 0xEB, 0xFF)
 ];
 
-pub static COLOR_MAP: [(u8, u8, u8, u8); 256] = [
+pub static COLOR_MAP: ColorTable = [
     (0x1a, 0x1a, 0x1a, 0xFF), (0x31, 0x31, 0x31, 0xFF), (0x47, 0x47, 0x47, 0xFF), (0x5d, 0x5d, 0x5d, 0xFF), (0x74, 0x74, 0x74, 0xFF), (0x8b, 0x8b, 0x8a, 0xFF), (0xa1, 0xa1, 0xa1, 0xFF), (0xb9, 0xb9, 0xb9, 0xFF),
     (0x1a, 0x1e, 0x00, 0xFF), (0x31, 0x35, 0x0a, 0xFF), (0x47, 0x4b, 0x21, 0xFF), (0x5e, 0x61, 0x35, 0xFF), (0x75, 0x78, 0x4d, 0xFF), (0x8c, 0x8f, 0x65, 0xFF), (0xa2, 0xa5, 0x7c, 0xFF), (0xb9, 0xbd, 0x93, 0xFF),
     (0x1a, 0x22, 0x00, 0xFF), (0x31, 0x39, 0x00, 0xFF), (0x47, 0x4f, 0x00, 0xFF), (0x5d, 0x65, 0x0e, 0xFF), (0x72, 0x7b, 0x24, 0xFF), (0x8b, 0x93, 0x3d, 0xFF), (0xa2, 0xaa, 0x52, 0xFF), (0xb9, 0xc2, 0x69, 0xFF),
@@ -83,7 +92,7 @@ pub static COLOR_MAP: [(u8, u8, u8, u8); 256] = [
 ];
 
 
-pub static COLOR_MAP_PERCEPTUALLY_AUTOMAPPED: [(u8, u8, u8, u8); 256] = [
+pub static COLOR_MAP_PERCEPTUALLY_AUTOMAPPED: ColorTable = [
     (0x1A, 0x1A, 0x19, 0xFF), (0x1B, 0x1B, 0x1B, 0xFF), (0x47, 0x47, 0x47, 0xFF), (0x5E, 0x5E, 0x5E, 0xFF), (0x75, 0x75, 0x75, 0xFF), (0xA1, 0xA1, 0xA1, 0xFF), (0xB9, 0xB9, 0xB9, 0xFF), (0xB9, 0xB9, 0xB9, 0xFF),
     (0x1A, 0x1A, 0x19, 0xFF), (0x04, 0x20, 0x17, 0xFF), (0x37, 0x4F, 0x2B, 0xFF), (0x4C, 0x65, 0x40, 0xFF), (0x6A, 0x9A, 0x56, 0xFF), (0x91, 0xA9, 0x82, 0xFF), (0xA8, 0xC0, 0x99, 0xFF), (0xA1, 0xBF, 0xB6, 0xFF),
     (0x1A, 0x1A, 0x19, 0xFF), (0x00, 0x28, 0x00, 0xFF), (0x27, 0x56, 0x12, 0xFF), (0x41, 0x8A, 0x21, 0xFF), (0x70, 0xB9, 0x4F, 0xFF), (0x85, 0xD0, 0x66, 0xFF), (0x97, 0xC7, 0x82, 0xFF), (0xA8, 0xC0, 0x99, 0xFF),
@@ -117,3 +126,47 @@ pub static COLOR_MAP_PERCEPTUALLY_AUTOMAPPED: [(u8, u8, u8, u8); 256] = [
     (0x1A, 0x1A, 0x19, 0xFF), (0x1A, 0x36, 0x2F, 0xFF), (0x1A, 0x54, 0x47, 0xFF), (0x2D, 0x88, 0x73, 0xFF), (0x5C, 0xB6, 0xA2, 0xFF), (0x72, 0xCD, 0xB8, 0xFF), (0x89, 0xC6, 0xB8, 0xFF), (0xA1, 0xBF, 0xB6, 0xFF),
     (0x1A, 0x1A, 0x19, 0xFF), (0x03, 0x3D, 0x31, 0xFF), (0x17, 0x72, 0x5D, 0xFF), (0x45, 0xA0, 0x8B, 0xFF), (0x72, 0xCD, 0xB8, 0xFF), (0x72, 0xCD, 0xB8, 0xFF), (0x72, 0xCD, 0xB8, 0xFF), (0x89, 0xC6, 0xB8, 0xFF),
 ];
+
+/// The colors framebuffer bytes are shown as.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum Palette {
+    /// `COLOR_MAP`.
+    #[default]
+    Default,
+    /// `COLOR_MAP_WRONG`.
+    Wrong,
+    /// `COLOR_MAP_PERCEPTUALLY_AUTOMAPPED`.
+    PerceptuallyAutomapped,
+    Custom(Box<ColorTable>),
+}
+
+impl Palette {
+    /// Looks up a built-in palette by the name a rom's metadata gives it: `default`, `wrong` or
+    /// `perceptually-automapped`.
+    pub fn by_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "default" => Some(Palette::Default),
+            "wrong" => Some(Palette::Wrong),
+            "perceptually-automapped" => Some(Palette::PerceptuallyAutomapped),
+            _ => None,
+        }
+    }
+
+    pub fn colors(&self) -> &ColorTable {
+        match self {
+            Palette::Default => &COLOR_MAP,
+            Palette::Wrong => &COLOR_MAP_WRONG,
+            Palette::PerceptuallyAutomapped => &COLOR_MAP_PERCEPTUALLY_AUTOMAPPED,
+            Palette::Custom(colors) => colors,
+        }
+    }
+}
+
+/// Converts `framebuffer`, one byte per pixel, into RGBA pixels in `out`.
+pub fn render_rgba(framebuffer: &[u8; (WIDTH * HEIGHT) as usize], out: &mut RgbaFrame, palette: &Palette) {
+    let colors = palette.colors();
+    for (pixel, &index) in out.chunks_exact_mut(4).zip(framebuffer) {
+        let (r, g, b, a) = colors[index as usize];
+        pixel.copy_from_slice(&[r, g, b, a]);
+    }
+}
@@ -0,0 +1,693 @@
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::{Display, Formatter};
+use gte_w65c02s::W65C02S;
+use crate::gametank_bus::{AcpBus, CpuBus};
+
+/// Which of the two 65C02s a breakpoint or watchpoint applies to.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum CpuId {
+    /// The main CPU.
+    Main,
+    /// The audio coprocessor.
+    Acp,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Access {
+    Read,
+    Write,
+}
+
+/// Which accesses a watchpoint triggers on.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum WatchKind {
+    Read,
+    Write,
+    ReadWrite,
+}
+
+impl WatchKind {
+    fn matches(&self, access: Access) -> bool {
+        match self {
+            WatchKind::Read => access == Access::Read,
+            WatchKind::Write => access == Access::Write,
+            WatchKind::ReadWrite => true,
+        }
+    }
+}
+
+/// Why execution was paused.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BreakReason {
+    /// A CPU was about to execute the instruction at `pc`.
+    Breakpoint { id: u32, cpu: CpuId, pc: u16 },
+    /// An instruction accessed a watched address. `rmw` is set for the locked accesses of
+    /// read-modify-write instructions.
+    Watchpoint { id: u32, cpu: CpuId, address: u16, access: Access, value: u8, rmw: bool },
+    /// A single step requested with `Emulator::step_instruction` finished.
+    Step { cpu: CpuId },
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct WatchHit {
+    pub id: u32,
+    pub address: u16,
+    pub access: Access,
+    pub value: u8,
+    pub rmw: bool,
+}
+
+#[derive(Clone, Debug)]
+struct WatchRange {
+    id: u32,
+    start: u16,
+    end: u16,
+    kind: WatchKind,
+}
+
+/// The watchpoints of one bus, checked on every access made through its `System` impl.
+#[derive(Clone, Debug, Default)]
+pub struct WatchList {
+    ranges: Vec<WatchRange>,
+    hits: Vec<WatchHit>,
+}
+
+impl WatchList {
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    pub(crate) fn add(&mut self, id: u32, start: u16, end: u16, kind: WatchKind) {
+        self.ranges.push(WatchRange { id, start, end, kind });
+    }
+
+    pub(crate) fn remove(&mut self, id: u32) -> bool {
+        let len = self.ranges.len();
+        self.ranges.retain(|r| r.id != id);
+        self.ranges.len() != len
+    }
+
+    #[inline(always)]
+    pub(crate) fn check(&mut self, address: u16, access: Access, value: u8, rmw: bool) {
+        if self.ranges.is_empty() {
+            return;
+        }
+        for r in &self.ranges {
+            if (r.start..=r.end).contains(&address) && r.kind.matches(access) {
+                self.hits.push(WatchHit { id: r.id, address, access, value, rmw });
+            }
+        }
+    }
+
+    pub(crate) fn take_hits(&mut self) -> Vec<WatchHit> {
+        core::mem::take(&mut self.hits)
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Breakpoint {
+    id: u32,
+    cpu: CpuId,
+    address: u16,
+    // only break while this 2M cartridge bank is mapped at `address`
+    bank: Option<u8>,
+    condition: Option<Condition>,
+}
+
+/// Breakpoints, watchpoint conditions and the current pause state.
+///
+/// Watchpoint ranges live on the buses themselves (`CpuBus::watches`, `AcpBus::watches`) so
+/// every access can be checked where it happens; use the `Emulator` methods to manage them.
+#[derive(Clone, Debug, Default)]
+pub struct Debugger {
+    next_id: u32,
+    breakpoints: Vec<Breakpoint>,
+    watch_conditions: Vec<(u32, CpuId, Option<Condition>)>,
+    pub(crate) break_reason: Option<BreakReason>,
+    // the instruction a cpu was paused on shouldn't immediately break again when resuming
+    pub(crate) skip_breakpoint: [bool; 2],
+    pub(crate) stepping: Option<CpuId>,
+}
+
+impl Debugger {
+    pub fn break_reason(&self) -> Option<BreakReason> {
+        self.break_reason
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.break_reason.is_some()
+    }
+
+    pub(crate) fn next_id(&mut self) -> u32 {
+        self.next_id += 1;
+        self.next_id
+    }
+
+    pub(crate) fn add_breakpoint(&mut self, cpu: CpuId, address: u16, bank: Option<u8>, condition: Option<Condition>) -> u32 {
+        let id = self.next_id();
+        self.breakpoints.push(Breakpoint { id, cpu, address, bank, condition });
+        id
+    }
+
+    pub(crate) fn add_watch_condition(&mut self, id: u32, cpu: CpuId, condition: Option<Condition>) {
+        self.watch_conditions.push((id, cpu, condition));
+    }
+
+    /// Removes a breakpoint or watchpoint condition. Returns the cpu it belonged to.
+    pub(crate) fn remove(&mut self, id: u32) -> Option<CpuId> {
+        if let Some(i) = self.breakpoints.iter().position(|b| b.id == id) {
+            return Some(self.breakpoints.remove(i).cpu);
+        }
+        let i = self.watch_conditions.iter().position(|(w, _, _)| *w == id)?;
+        Some(self.watch_conditions.remove(i).1)
+    }
+
+    /// Lists the breakpoints as (id, cpu, address).
+    pub fn breakpoints(&self) -> impl Iterator<Item = (u32, CpuId, u16)> + '_ {
+        self.breakpoints.iter().map(|b| (b.id, b.cpu, b.address))
+    }
+
+    /// Checks whether `cpu` should stop before executing the instruction at its pc.
+    /// `at_instruction` is false while the cpu is waiting for an interrupt or stopped.
+    pub(crate) fn check_breakpoints(&mut self, cpu: CpuId, at_instruction: bool, ctx: &dyn DebugContext) -> bool {
+        let index = cpu as usize;
+        if core::mem::take(&mut self.skip_breakpoint[index]) || !at_instruction || self.breakpoints.is_empty() {
+            return false;
+        }
+
+        let pc = ctx.register(Register::PC);
+        let hit = self.breakpoints.iter().find(|b| {
+            b.cpu == cpu && b.address == pc
+                && b.bank.is_none_or(|bank| ctx.bank(pc).is_none_or(|mapped| mapped == bank))
+                && b.condition.as_ref().is_none_or(|c| c.evaluate(ctx) != 0)
+        });
+        if let Some(b) = hit {
+            self.break_reason = Some(BreakReason::Breakpoint { id: b.id, cpu, pc });
+            return true;
+        }
+        false
+    }
+
+    /// Turns the raw hits recorded by a bus into a pause, if any hit's condition holds.
+    pub(crate) fn check_watch_hits(&mut self, cpu: CpuId, hits: Vec<WatchHit>, ctx: &dyn DebugContext) -> bool {
+        for hit in hits {
+            let condition = self.watch_conditions.iter().find(|(id, _, _)| *id == hit.id);
+            let triggered = match condition {
+                Some((_, _, Some(c))) => c.evaluate(ctx) != 0,
+                Some((_, _, None)) => true,
+                None => false,
+            };
+            if triggered {
+                self.break_reason = Some(BreakReason::Watchpoint {
+                    id: hit.id,
+                    cpu,
+                    address: hit.address,
+                    access: hit.access,
+                    value: hit.value,
+                    rmw: hit.rmw,
+                });
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Called after `cpu` finishes an instruction, to end a single step.
+    pub(crate) fn instruction_done(&mut self, cpu: CpuId) {
+        if self.stepping == Some(cpu) && self.break_reason.is_none() {
+            self.stepping = None;
+            self.break_reason = Some(BreakReason::Step { cpu });
+        }
+    }
+
+    pub(crate) fn resume(&mut self) {
+        if let Some(BreakReason::Breakpoint { cpu, .. }) = self.break_reason {
+            self.skip_breakpoint[cpu as usize] = true;
+        }
+        self.break_reason = None;
+        self.stepping = None;
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Register {
+    A,
+    X,
+    Y,
+    S,
+    P,
+    PC,
+}
+
+/// What a condition can look at: one cpu's registers and a side-effect free view of its bus.
+pub trait DebugContext {
+    fn register(&self, register: Register) -> u16;
+    fn peek(&self, address: u16) -> u8;
+    /// The 2M cartridge bank mapped at `address`, if it's in banked rom.
+    fn bank(&self, address: u16) -> Option<u8> {
+        None
+    }
+}
+
+fn cpu_register(cpu: &W65C02S, register: Register) -> u16 {
+    match register {
+        Register::A => cpu.get_a() as u16,
+        Register::X => cpu.get_x() as u16,
+        Register::Y => cpu.get_y() as u16,
+        Register::S => cpu.get_s() as u16,
+        Register::P => cpu.get_p() as u16,
+        Register::PC => cpu.get_pc(),
+    }
+}
+
+pub(crate) struct MainContext<'a> {
+    pub cpu: &'a W65C02S,
+    pub bus: &'a CpuBus,
+}
+
+impl DebugContext for MainContext<'_> {
+    fn register(&self, register: Register) -> u16 {
+        cpu_register(self.cpu, register)
+    }
+
+    fn peek(&self, address: u16) -> u8 {
+        self.bus.peek_byte(address)
+    }
+
+    fn bank(&self, address: u16) -> Option<u8> {
+        self.bus.bank_at(address)
+    }
+}
+
+pub(crate) struct AcpContext<'a> {
+    pub cpu: &'a W65C02S,
+    pub bus: &'a AcpBus,
+}
+
+impl DebugContext for AcpContext<'_> {
+    fn register(&self, register: Register) -> u16 {
+        cpu_register(self.cpu, register)
+    }
+
+    fn peek(&self, address: u16) -> u8 {
+        self.bus.read_byte(address)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ExprError {
+    UnexpectedEnd,
+    UnexpectedChar(usize),
+    BadNumber(usize),
+    TrailingInput(usize),
+    /// A name that is neither a register nor a known symbol.
+    UnknownSymbol(usize),
+    /// A symbol breakpoint names a symbol the symbol table doesn't have.
+    NoSuchSymbol(String),
+    /// The expression nests deeper than `MAX_DEPTH` operators, brackets and parentheses.
+    TooDeep,
+}
+
+impl Display for ExprError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            ExprError::UnexpectedEnd => write!(f, "unexpected end of expression"),
+            ExprError::UnexpectedChar(at) => write!(f, "unexpected character at {}", at),
+            ExprError::BadNumber(at) => write!(f, "bad number at {}", at),
+            ExprError::TrailingInput(at) => write!(f, "unexpected input at {}", at),
+            ExprError::UnknownSymbol(at) => write!(f, "unknown symbol at {}", at),
+            ExprError::NoSuchSymbol(name) => write!(f, "no symbol named `{}`", name),
+            ExprError::TooDeep => write!(f, "expression is nested too deeply"),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum BinOp {
+    Or, And,
+    Eq, Ne, Lt, Le, Gt, Ge,
+    BitOr, BitXor, BitAnd,
+    Add, Sub,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Expr {
+    Number(u16),
+    Register(Register),
+    /// `[address]`, a byte of memory
+    Memory(Box<Expr>),
+    Not(Box<Expr>),
+    Binary(BinOp, Box<Expr>, Box<Expr>),
+}
+
+/// A breakpoint/watchpoint condition such as `A == $10 && [$0200] != 0`.
+///
+/// Operands are registers (`A`, `X`, `Y`, `S`, `P`, `PC`), numbers (`$1F`, `%0101`, `42`) and
+/// memory bytes (`[$0200]`, `[PC+1]`). Operators, loosest first: `||`, `&&`,
+/// `== != < <= > >=`, `|`, `^`, `&`, `+ -`, unary `!`. Anything nonzero is true.
+/// Operators, brackets and parentheses may nest up to `MAX_DEPTH` deep.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Condition {
+    expr: Expr,
+}
+
+impl Condition {
+    pub fn parse(source: &str) -> Result<Self, ExprError> {
+        Self::parse_with_symbols(source, &|_| None)
+    }
+
+    /// Like `parse`, but names that aren't registers are looked up with `resolve`, so
+    /// conditions like `[player_x] > 100` work.
+    pub fn parse_with_symbols(source: &str, resolve: &dyn Fn(&str) -> Option<u16>) -> Result<Self, ExprError> {
+        let mut parser = Parser { src: source.as_bytes(), pos: 0, resolve, depth: 0 };
+        let expr = parser.binary(0)?;
+        parser.skip_ws();
+        if parser.pos != parser.src.len() {
+            return Err(ExprError::TrailingInput(parser.pos));
+        }
+        Ok(Self { expr })
+    }
+
+    pub fn evaluate(&self, ctx: &dyn DebugContext) -> u16 {
+        eval(&self.expr, ctx)
+    }
+}
+
+fn eval(expr: &Expr, ctx: &dyn DebugContext) -> u16 {
+    match expr {
+        Expr::Number(n) => *n,
+        Expr::Register(r) => ctx.register(*r),
+        Expr::Memory(addr) => ctx.peek(eval(addr, ctx)) as u16,
+        Expr::Not(e) => (eval(e, ctx) == 0) as u16,
+        Expr::Binary(op, l, r) => {
+            let l = eval(l, ctx);
+            // short-circuit, so `[ptr] ...` style guards behave as expected
+            match op {
+                BinOp::Or if l != 0 => return 1,
+                BinOp::And if l == 0 => return 0,
+                _ => {}
+            }
+            let r = eval(r, ctx);
+            match op {
+                BinOp::Or | BinOp::And => (r != 0) as u16,
+                BinOp::Eq => (l == r) as u16,
+                BinOp::Ne => (l != r) as u16,
+                BinOp::Lt => (l < r) as u16,
+                BinOp::Le => (l <= r) as u16,
+                BinOp::Gt => (l > r) as u16,
+                BinOp::Ge => (l >= r) as u16,
+                BinOp::BitOr => l | r,
+                BinOp::BitXor => l ^ r,
+                BinOp::BitAnd => l & r,
+                BinOp::Add => l.wrapping_add(r),
+                BinOp::Sub => l.wrapping_sub(r),
+            }
+        }
+    }
+}
+
+// precedence levels, loosest first
+const LEVELS: &[&[(&str, BinOp)]] = &[
+    &[("||", BinOp::Or)],
+    &[("&&", BinOp::And)],
+    &[("==", BinOp::Eq), ("!=", BinOp::Ne), ("<=", BinOp::Le), (">=", BinOp::Ge), ("<", BinOp::Lt), (">", BinOp::Gt)],
+    &[("|", BinOp::BitOr)],
+    &[("^", BinOp::BitXor)],
+    &[("&", BinOp::BitAnd)],
+    &[("+", BinOp::Add), ("-", BinOp::Sub)],
+];
+
+/// How deep an expression tree may get. Parsing, evaluating and dropping expressions all
+/// recurse, so this keeps a pathological condition from overflowing the stack.
+pub const MAX_DEPTH: usize = 128;
+
+struct Parser<'a> {
+    src: &'a [u8],
+    resolve: &'a dyn Fn(&str) -> Option<u16>,
+    pos: usize,
+    // depth of the expression tree above what's being parsed
+    depth: usize,
+}
+
+impl Parser<'_> {
+    fn skip_ws(&mut self) {
+        while self.src.get(self.pos).is_some_and(|c| c.is_ascii_whitespace()) {
+            self.pos += 1;
+        }
+    }
+
+    fn eat(&mut self, token: &str) -> bool {
+        self.skip_ws();
+        let token = token.as_bytes();
+        if !self.src[self.pos..].starts_with(token) {
+            return false;
+        }
+        // don't mistake `||` for `|` or `&&` for `&`
+        if token.len() == 1 && self.src.get(self.pos + 1) == Some(&token[0]) && matches!(token[0], b'|' | b'&') {
+            return false;
+        }
+        self.pos += token.len();
+        true
+    }
+
+    fn descend(&mut self) -> Result<(), ExprError> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return Err(ExprError::TooDeep);
+        }
+        Ok(())
+    }
+
+    fn binary(&mut self, level: usize) -> Result<Expr, ExprError> {
+        if level == LEVELS.len() {
+            return self.unary();
+        }
+        let depth = self.depth;
+        let mut lhs = self.binary(level + 1)?;
+        'outer: loop {
+            for (token, op) in LEVELS[level] {
+                if self.eat(token) {
+                    // operators are left associative, so each one puts everything before it
+                    // a level deeper
+                    self.descend()?;
+                    let rhs = self.binary(level + 1)?;
+                    lhs = Expr::Binary(*op, Box::new(lhs), Box::new(rhs));
+                    continue 'outer;
+                }
+            }
+            self.depth = depth;
+            return Ok(lhs);
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expr, ExprError> {
+        if self.eat("!") {
+            self.descend()?;
+            let e = self.unary()?;
+            self.depth -= 1;
+            return Ok(Expr::Not(Box::new(e)));
+        }
+        if self.eat("(") {
+            self.descend()?;
+            let e = self.binary(0)?;
+            self.depth -= 1;
+            return if self.eat(")") { Ok(e) } else { Err(self.error()) };
+        }
+        if self.eat("[") {
+            self.descend()?;
+            let e = self.binary(0)?;
+            self.depth -= 1;
+            return if self.eat("]") { Ok(Expr::Memory(Box::new(e))) } else { Err(self.error()) };
+        }
+        self.skip_ws();
+        let start = self.pos;
+        let (radix, digits_start) = match self.src.get(self.pos) {
+            Some(b'$') => (16, self.pos + 1),
+            Some(b'%') => (2, self.pos + 1),
+            Some(c) if c.is_ascii_digit() => (10, self.pos),
+            Some(&c) if is_name_char(c) => return self.name(),
+            _ => return Err(self.error()),
+        };
+        self.pos = digits_start;
+        while self.src.get(self.pos).is_some_and(|c| c.is_ascii_alphanumeric()) {
+            self.pos += 1;
+        }
+        let digits = core::str::from_utf8(&self.src[digits_start..self.pos]).map_err(|_| ExprError::BadNumber(start))?;
+        u16::from_str_radix(digits, radix).map(Expr::Number).map_err(|_| ExprError::BadNumber(start))
+    }
+
+    fn name(&mut self) -> Result<Expr, ExprError> {
+        let start = self.pos;
+        while self.src.get(self.pos).is_some_and(|&c| is_name_char(c) || c.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        let name = &self.src[start..self.pos];
+        let register = match name.to_ascii_uppercase().as_slice() {
+            b"A" => Register::A,
+            b"X" => Register::X,
+            b"Y" => Register::Y,
+            b"S" | b"SP" => Register::S,
+            b"P" => Register::P,
+            b"PC" => Register::PC,
+            _ => {
+                // names are ascii, so this can't fail
+                let name = core::str::from_utf8(name).unwrap_or_default();
+                return (self.resolve)(name).map(Expr::Number).ok_or(ExprError::UnknownSymbol(start));
+            }
+        };
+        Ok(Expr::Register(register))
+    }
+
+    fn error(&self) -> ExprError {
+        if self.pos >= self.src.len() {
+            ExprError::UnexpectedEnd
+        } else {
+            ExprError::UnexpectedChar(self.pos)
+        }
+    }
+}
+
+fn is_name_char(c: u8) -> bool {
+    c.is_ascii_alphabetic() || matches!(c, b'_' | b'@' | b'.')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+    use crate::test_support::emulator;
+
+    struct Context;
+
+    impl DebugContext for Context {
+        fn register(&self, register: Register) -> u16 {
+            match register {
+                Register::A => 0x12,
+                Register::X => 0x34,
+                Register::Y => 0x56,
+                Register::S => 0xFD,
+                Register::P => 0x24,
+                Register::PC => 0xE000,
+            }
+        }
+
+        // every byte of memory holds the low byte of its address
+        fn peek(&self, address: u16) -> u8 {
+            address as u8
+        }
+    }
+
+    fn eval(source: &str) -> u16 {
+        Condition::parse(source).unwrap().evaluate(&Context)
+    }
+
+    #[test]
+    fn numbers() {
+        assert_eq!(eval("42"), 42);
+        assert_eq!(eval("$1F"), 0x1F);
+        assert_eq!(eval("%0101"), 5);
+        assert_eq!(eval("$FFFF + 2"), 1);
+        assert_eq!(eval("0 - 1"), 0xFFFF);
+    }
+
+    #[test]
+    fn precedence() {
+        assert_eq!(eval("1 + 2 & 6"), 2);
+        assert_eq!(eval("1 | 2 ^ 3"), 1);
+        assert_eq!(eval("2 ^ 3 & 1"), 3);
+        assert_eq!(eval("1 | 2 == 3"), 1);
+        assert_eq!(eval("3 == 3 && 2 < 1"), 0);
+        assert_eq!(eval("0 && 1 || 1"), 1);
+        assert_eq!(eval("1 || 0 && 0"), 1);
+        assert_eq!(eval("!0 + 1"), 2);
+        assert_eq!(eval("!(0 + 1)"), 0);
+        assert_eq!(eval("10 - 3 - 2"), 5);
+        assert_eq!(eval("(1 + 2) & 6"), 2);
+    }
+
+    #[test]
+    fn comparisons() {
+        for (source, value) in [("1 < 2", 1), ("2 <= 2", 1), ("3 > 2", 1), ("2 >= 3", 0), ("2 != 2", 0), ("2 == 2", 1)] {
+            assert_eq!(eval(source), value, "{}", source);
+        }
+    }
+
+    #[test]
+    fn registers() {
+        assert_eq!(eval("A"), 0x12);
+        assert_eq!(eval("x + y"), 0x34 + 0x56);
+        assert_eq!(eval("S == SP"), 1);
+        assert_eq!(eval("P & $04"), 0x04);
+        assert_eq!(eval("PC == $E000"), 1);
+    }
+
+    #[test]
+    fn memory_reads() {
+        assert_eq!(eval("[$0234]"), 0x34);
+        assert_eq!(eval("[PC + 1]"), 0x01);
+        assert_eq!(eval("[[X]]"), 0x34);
+        assert_eq!(eval("[$10] == $10 && [A] != 0"), 1);
+    }
+
+    #[test]
+    fn symbols() {
+        let resolve = |name: &str| match name {
+            "player_x" => Some(0x0210),
+            "_score.lo" => Some(0x0220),
+            _ => None,
+        };
+        let condition = Condition::parse_with_symbols("[player_x] + _score.lo", &resolve).unwrap();
+        assert_eq!(condition.evaluate(&Context), 0x10 + 0x0220);
+        assert_eq!(Condition::parse_with_symbols("A == player_y", &resolve), Err(ExprError::UnknownSymbol(5)));
+        // registers win over symbols of the same name
+        assert_eq!(Condition::parse_with_symbols("a", &|_| Some(1)).unwrap().evaluate(&Context), 0x12);
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(Condition::parse(""), Err(ExprError::UnexpectedEnd));
+        assert_eq!(Condition::parse("A =="), Err(ExprError::UnexpectedEnd));
+        assert_eq!(Condition::parse("(1"), Err(ExprError::UnexpectedEnd));
+        assert_eq!(Condition::parse("A == #1"), Err(ExprError::UnexpectedChar(5)));
+        assert_eq!(Condition::parse("[1)"), Err(ExprError::UnexpectedChar(2)));
+        assert_eq!(Condition::parse("$10000"), Err(ExprError::BadNumber(0)));
+        assert_eq!(Condition::parse("1 + $"), Err(ExprError::BadNumber(4)));
+        assert_eq!(Condition::parse("%102"), Err(ExprError::BadNumber(0)));
+        assert_eq!(Condition::parse("12ab"), Err(ExprError::BadNumber(0)));
+        assert_eq!(Condition::parse("1 2"), Err(ExprError::TrailingInput(2)));
+        assert_eq!(Condition::parse("A )"), Err(ExprError::TrailingInput(2)));
+        assert_eq!(Condition::parse("foo"), Err(ExprError::UnknownSymbol(0)));
+    }
+
+    #[test]
+    fn no_such_symbol() {
+        let mut emulator = emulator();
+        let error = emulator.add_symbol_breakpoint(CpuId::Main, "missing", None).unwrap_err();
+        assert_eq!(error, ExprError::NoSuchSymbol("missing".into()));
+        assert_eq!(error.to_string(), "no symbol named `missing`");
+    }
+
+    #[test]
+    fn nesting_limit() {
+        let nested = |open: &str, close: &str, depth: usize| open.repeat(depth) + "1" + &close.repeat(depth);
+        assert_eq!(eval(&nested("(", ")", MAX_DEPTH)), 1);
+        assert_eq!(eval(&nested("[", "]", MAX_DEPTH)), 1);
+        assert_eq!(eval(&nested("!", "", MAX_DEPTH)), 1);
+        assert_eq!(Condition::parse(&nested("(", ")", MAX_DEPTH + 1)), Err(ExprError::TooDeep));
+        assert_eq!(Condition::parse(&nested("[", "]", MAX_DEPTH + 1)), Err(ExprError::TooDeep));
+        assert_eq!(Condition::parse(&nested("!", "", MAX_DEPTH + 1)), Err(ExprError::TooDeep));
+
+        // long chains of operators nest as deep as parentheses do
+        let chain = |ops: usize| "1".to_string() + &" + 1".repeat(ops);
+        assert_eq!(eval(&chain(MAX_DEPTH)), MAX_DEPTH as u16 + 1);
+        assert_eq!(Condition::parse(&chain(MAX_DEPTH + 1)), Err(ExprError::TooDeep));
+        assert_eq!(eval(&("(".to_string() + &chain(MAX_DEPTH - 1) + ")")), MAX_DEPTH as u16);
+
+        // and way past the limit, fail without running out of stack
+        assert_eq!(Condition::parse(&nested("(", ")", 200_000)), Err(ExprError::TooDeep));
+        assert_eq!(Condition::parse(&nested("!", "", 200_000)), Err(ExprError::TooDeep));
+        assert_eq!(Condition::parse(&chain(200_000)), Err(ExprError::TooDeep));
+    }
+}
@@ -2,25 +2,56 @@ use alloc::boxed::Box;
 use alloc::vec;
 use alloc::vec::Vec;
 use gte_w65c02s::{System, W65C02S};
+use gte_w65c02s::disasm::{self, Disassembler, Instruction};
 use log::{debug, error, info, warn};
-use gte_w65c02s::State::AwaitingInterrupt;
-use core::fmt::{Debug, Formatter};
+use gte_w65c02s::State::{AwaitingInterrupt, Running};
+use core::fmt::{Debug, Formatter, Write};
 use bytemuck::bytes_of;
 use heapless::{FnvIndexMap};
 use rtrb::PushError;
 use crate::audio_output::GameTankAudio;
 use crate::blitter::Blitter;
-use crate::cartridges::CartridgeType;
+use crate::color_map::{self, Palette, RgbaFrame};
+use crate::ntsc::NtscFilter;
+use crate::cartridges::{CartridgeType, RomError};
+use crate::cartridges::storage::{SaveStorage, StorageError};
+use crate::debugger::{AcpContext, Condition, CpuId, Debugger, ExprError, MainContext, WatchKind};
+use crate::rom_info::RomInfo;
+use crate::symbols::{Label, SymbolTable};
+use crate::trace::{TraceEntry, Tracer};
+use crate::uninit::UninitRead;
+use crate::video::{BeamPosition, VideoOutput};
 use crate::emulator::PlayState::{Paused, Playing, WasmInit};
-use crate::gametank_bus::{AcpBus, Bus, CpuBus};
+use crate::gametank_bus::{AcpBus, Bus, CpuBus, PowerOnFill, ARAM_SIZE};
 use crate::inputs::{ControllerButton, InputCommand, KeyState};
 use crate::inputs::ControllerButton::{Down, Left, Right, Start, Up, A, B, C};
 use crate::inputs::InputCommand::{Controller1, Controller2, HardReset, PlayPause, SoftReset};
-use crate::inputs::KeyState::JustReleased;
+use crate::inputs::KeyState::{JustPressed, JustReleased};
+use crate::movie::{Movie, MovieMode, MovieSession};
+use crate::rewind::RewindBuffer;
+use crate::save_state;
+use crate::save_state::*;
 
 pub const WIDTH: u32 = 128;
 pub const HEIGHT: u32 = 128;
 
+/// CPU cycles between two vblanks.
+pub const CYCLES_PER_FRAME: i32 = 59659;
+
+/// What happened during a call to one of the `run_*` methods.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct RunSummary {
+    /// CPU cycles actually executed. Instructions are never split, so this may be slightly
+    /// more than requested; the overshoot is subtracted from the next `run_cycles` call.
+    pub cycles: u64,
+    /// Number of vblanks that occurred.
+    pub vblanks: u32,
+    /// Whether a vblank NMI was raised on the main CPU.
+    pub nmi_fired: bool,
+    /// Number of blits that ran to completion.
+    pub blits_completed: u32,
+}
+
 #[derive(Copy, Clone, Debug, PartialEq)]
 pub enum PlayState {
     WasmInit,
@@ -39,8 +70,29 @@ pub struct Emulator<Clock: TimeDaemon> {
     pub acp: W65C02S,
 
     pub blitter: Blitter,
+    video: VideoOutput,
 
     pub clock_cycles_to_vblank: i32,
+    pub acp_cycle_accumulator: i32,
+    // cycles run past the target of the last `run_cycles`
+    cycle_overshoot: u64,
+
+    /// What memory holds after `init` and `hard_reset`.
+    pub power_on_fill: PowerOnFill,
+    pub rom_hash: u64,
+    rom_info: RomInfo,
+    pub frame_count: u64,
+    pub rewind: Option<RewindBuffer>,
+    pub movie: Option<MovieSession>,
+    pub debugger: Debugger,
+    /// Labels and source lines for the main cpu's program, and for the ACP's.
+    pub symbols: SymbolTable,
+    pub acp_symbols: SymbolTable,
+    pub tracer: Option<Tracer>,
+    uninit_callback: Option<Box<dyn FnMut(UninitRead) + Send>>,
+    /// Where the cartridge's flash is persisted. Set it before `load_rom`, which loads the
+    /// saved sectors from it; changes are written back at the end of every frame.
+    pub save_storage: Option<Box<dyn SaveStorage + Send>>,
 
     pub last_emu_tick: f64,
     pub cpu_ns_per_cycle: f64,
@@ -57,16 +109,193 @@ pub struct Emulator<Clock: TimeDaemon> {
 }
 
 impl <Clock: TimeDaemon> Emulator<Clock> {
-    pub fn load_rom(&mut self, bytes: &[u8]) {
-        warn!("loading new rom from memory, size: {}", bytes.len());
-        self.cpu_bus.cartridge = CartridgeType::from_slice(bytes);
+    /// Inserts a new cartridge and resets both cpus. The mapper is the one the file's metadata
+    /// header names, or else the one its size calls for. A rom that can't be loaded leaves the
+    /// current cartridge in place.
+    pub fn load_rom(&mut self, bytes: &[u8]) -> Result<(), RomError> {
+        let (info, image) = RomInfo::split_header(bytes)?;
+        self.insert_rom(image, info.unwrap_or_default())
+    }
+
+    /// Like `load_rom`, with metadata from a sidecar file taking the place of the file's
+    /// header.
+    pub fn load_rom_with_info(&mut self, bytes: &[u8], info: RomInfo) -> Result<(), RomError> {
+        let (_, image) = RomInfo::split_header(bytes)?;
+        self.insert_rom(image, info)
+    }
+
+    /// Draws the picture on screen, see `video_frame`, into `out` as RGBA.
+    pub fn render_rgba(&self, out: &mut RgbaFrame, palette: &Palette) {
+        color_map::render_rgba(&self.video.image, out, palette);
+    }
+
+    /// Like `render_rgba`, through a simulation of the composite video signal.
+    pub fn render_ntsc(&self, out: &mut RgbaFrame, filter: &NtscFilter) {
+        filter.render_rgba(&self.video.image, self.frame_count, out);
+    }
+
+    /// The picture on screen, one byte per pixel. Each row holds what the displayed
+    /// framebuffer had in it when the beam started drawing the row, so rows below the beam
+    /// are still from the previous frame.
+    pub fn video_frame(&self) -> &[u8] {
+        &self.video.image[..]
+    }
+
+    /// Where the video beam is in the current frame.
+    pub fn beam_position(&self) -> BeamPosition {
+        BeamPosition::at(self.frame_cycle())
+    }
+
+    /// CPU cycles since the last vblank.
+    fn frame_cycle(&self) -> u32 {
+        (CYCLES_PER_FRAME - self.clock_cycles_to_vblank).max(0) as u32
+    }
+
+    /// The metadata of the loaded rom, with the cartridge and save type filled in from the
+    /// cartridge in use when the rom didn't give them.
+    pub fn rom_info(&self) -> &RomInfo {
+        &self.rom_info
+    }
+
+    fn insert_rom(&mut self, image: &[u8], info: RomInfo) -> Result<(), RomError> {
+        warn!("loading new rom from memory, size: {}", image.len());
+        self.cpu_bus.cartridge = CartridgeType::from_slice_as(image, info.cartridge)?;
+        self.rom_info = info.complete(self.cpu_bus.cartridge.kind());
+        if let Some(title) = &self.rom_info.title {
+            warn!(" - {}", title);
+        }
+        self.rom_hash = save_state::fnv1a64(self.cpu_bus.cartridge.image());
+        if let Some(rewind) = &mut self.rewind {
+            rewind.clear();
+        }
         warn!(" - cartridge loaded from memory");
+        if let Some(storage) = &mut self.save_storage {
+            match self.cpu_bus.cartridge.load_save(storage.as_mut()) {
+                Ok(()) => { warn!(" - save data loaded") }
+                Err(e) => { error!(" - could not load save data: {}", e) }
+            }
+        }
         self.cpu.reset();
         warn!(" - cpu reset");
         self.acp.reset();
         warn!(" - acp reset");
         self.blitter.clear_irq_trigger();
         warn!(" - blitter irq cleared");
+        Ok(())
+    }
+
+    /// Writes the flash sectors the game changed to `save_storage`, if there is one.
+    /// Frontends should call this before exiting, since a flash operation in progress at the
+    /// last vblank is only persisted here.
+    pub fn flush_save(&mut self) -> Result<(), StorageError> {
+        match &mut self.save_storage {
+            Some(storage) => { self.cpu_bus.cartridge.persist(storage.as_mut()) }
+            None => Ok(()),
+        }
+    }
+
+    /// Serializes the whole machine into a save state file: both CPUs, every bus and memory,
+    /// the blitter, the vblank countdown and the picture on screen. The cartridge image itself
+    /// is only referenced by its hash, so the state can only be restored with the same rom
+    /// loaded.
+    pub fn save_state(&self) -> Vec<u8> {
+        let mut out = StateWriter::new();
+        save_state::write_header(&mut out, self.rom_hash);
+        out.put_chunk(CHUNK_CPU, |out| self.cpu.save(out));
+        out.put_chunk(CHUNK_ACP, |out| {
+            self.acp.save(out);
+            self.acp_bus.save(out);
+            out.put_i32(self.acp_cycle_accumulator);
+        });
+        out.put_chunk(CHUNK_SYSTEM, |out| {
+            self.cpu_bus.system_control.save(out);
+            out.put_i32(self.clock_cycles_to_vblank);
+            out.put_u8(self.cpu_bus.data_bus);
+        });
+        out.put_chunk(CHUNK_BLITTER, |out| {
+            self.cpu_bus.blitter.save(out);
+            self.blitter.save(out);
+        });
+        out.put_chunk(CHUNK_RAM, |out| self.cpu_bus.save_ram(out));
+        out.put_chunk(CHUNK_VRAM, |out| self.cpu_bus.save_vram(out));
+        out.put_chunk(CHUNK_FRAMEBUFFERS, |out| self.cpu_bus.save_framebuffers(out));
+        out.put_chunk(CHUNK_ARAM, |out| out.put_bytes(&self.acp_bus.aram.to_bytes()));
+        out.put_chunk(CHUNK_CARTRIDGE, |out| self.cpu_bus.cartridge.save(out));
+        out.put_chunk(CHUNK_VIDEO, |out| out.put_bytes(&self.video.image[..]));
+        save_state::finish_file(out)
+    }
+
+    /// Restores a file produced by `save_state`. The file is fully validated before anything
+    /// is touched, and the previous state is put back if a chunk turns out to be malformed.
+    pub fn load_state(&mut self, bytes: &[u8]) -> Result<(), StateError> {
+        let file = StateFile::parse(bytes, self.rom_hash)?;
+        for tag in [CHUNK_CPU, CHUNK_ACP, CHUNK_SYSTEM, CHUNK_BLITTER, CHUNK_RAM, CHUNK_VRAM, CHUNK_FRAMEBUFFERS, CHUNK_ARAM, CHUNK_CARTRIDGE] {
+            file.chunk(tag)?;
+        }
+        if file.version >= 5 {
+            file.chunk(CHUNK_VIDEO)?;
+        }
+
+        let backup = self.save_state();
+        if let Err(e) = self.apply_state(&file) {
+            let backup = StateFile::parse(&backup, self.rom_hash).expect("backup state is valid");
+            self.apply_state(&backup).expect("backup state is valid");
+            return Err(e);
+        }
+        // states don't record which bytes were written, so keep the detector from reporting
+        // memory the game may well have initialized before the state was made
+        self.cpu_bus.mark_memory_written();
+        self.wait_counter = 0;
+        Ok(())
+    }
+
+    fn apply_state(&mut self, file: &StateFile) -> Result<(), StateError> {
+        read_chunk(file.chunk(CHUNK_CPU)?, |input| self.cpu.load(input))?;
+        read_chunk(file.chunk(CHUNK_ACP)?, |input| {
+            self.acp.load(input)?;
+            self.acp_bus.load(input)?;
+            self.acp_cycle_accumulator = input.get_i32()?;
+            Ok(())
+        })?;
+        read_chunk(file.chunk(CHUNK_SYSTEM)?, |input| {
+            if file.version < 2 {
+                self.cpu_bus.system_control.load_v1(input)?;
+            } else {
+                self.cpu_bus.system_control.load(input)?;
+            }
+            self.clock_cycles_to_vblank = input.get_i32()?;
+            if file.version >= 4 {
+                self.cpu_bus.data_bus = input.get_u8()?;
+            }
+            Ok(())
+        })?;
+        read_chunk(file.chunk(CHUNK_BLITTER)?, |input| {
+            self.cpu_bus.blitter.load(input)?;
+            self.blitter.load(input)
+        })?;
+        read_chunk(file.chunk(CHUNK_RAM)?, |input| self.cpu_bus.load_ram(input))?;
+        read_chunk(file.chunk(CHUNK_VRAM)?, |input| self.cpu_bus.load_vram(input))?;
+        read_chunk(file.chunk(CHUNK_FRAMEBUFFERS)?, |input| self.cpu_bus.load_framebuffers(input))?;
+        read_chunk(file.chunk(CHUNK_ARAM)?, |input| {
+            let mut aram = [0; ARAM_SIZE];
+            input.read_into(&mut aram)?;
+            self.acp_bus.aram.set_bytes(&aram);
+            Ok(())
+        })?;
+        read_chunk(file.chunk(CHUNK_CARTRIDGE)?, |input| {
+            if file.version < 3 {
+                self.cpu_bus.cartridge.load_v2(input)
+            } else {
+                self.cpu_bus.cartridge.load(input)
+            }
+        })?;
+        if file.version >= 5 {
+            read_chunk(file.chunk(CHUNK_VIDEO)?, |input| input.read_into(&mut self.video.image[..]))?;
+        } else {
+            self.video.image.copy_from_slice(&self.cpu_bus.read_full_framebuffer()[..]);
+        }
+        self.video.resume_at(self.frame_cycle());
+        Ok(())
     }
 }
 
@@ -84,6 +313,64 @@ impl <Clock: TimeDaemon> Debug for Emulator<Clock> {
         Ok(())
     }}
 
+/// Forks the emulator: the clone runs on from the same state, independently of the original.
+/// It has its own copy of audio RAM, and leaves out what's tied to the outside world: it has
+/// no save storage, audio output or uninitialized read detector, and its tracer doesn't write
+/// to the original's output. Flash writes made by the fork are never persisted.
+impl <Clock: TimeDaemon + Clone> Clone for Emulator<Clock> {
+    fn clone(&self) -> Self {
+        let mut cpu_bus = self.cpu_bus.clone();
+        let mut acp_bus = self.acp_bus.clone();
+        acp_bus.aram = cpu_bus.aram.share();
+        cpu_bus.uninit_hits = None;
+        acp_bus.uninit_hits = None;
+
+        Emulator {
+            cpu_bus,
+            acp_bus,
+            cpu: self.cpu,
+            acp: self.acp,
+            blitter: self.blitter.clone(),
+            video: self.video.clone(),
+            clock_cycles_to_vblank: self.clock_cycles_to_vblank,
+            acp_cycle_accumulator: self.acp_cycle_accumulator,
+            cycle_overshoot: self.cycle_overshoot,
+            power_on_fill: self.power_on_fill,
+            rom_hash: self.rom_hash,
+            rom_info: self.rom_info.clone(),
+            frame_count: self.frame_count,
+            rewind: self.rewind.clone(),
+            movie: self.movie.clone(),
+            debugger: self.debugger.clone(),
+            symbols: self.symbols.clone(),
+            acp_symbols: self.acp_symbols.clone(),
+            tracer: self.tracer.clone(),
+            uninit_callback: None,
+            save_storage: None,
+            last_emu_tick: self.last_emu_tick,
+            cpu_ns_per_cycle: self.cpu_ns_per_cycle,
+            cpu_frequency_hz: self.cpu_frequency_hz,
+            last_render_time: self.last_render_time,
+            audio_out: None,
+            target_sample_rate: self.target_sample_rate,
+            play_state: self.play_state,
+            wait_counter: self.wait_counter,
+            input_state: self.input_state.clone(),
+            clock: self.clock.clone(),
+        }
+    }
+}
+
+// frontends run the emulator on its own thread
+const _: () = {
+    const fn assert_send<T: Send>() {}
+    assert_send::<Emulator<SendClock>>();
+    struct SendClock;
+    impl TimeDaemon for SendClock {
+        fn get_now_ms(&self) -> f64 { 0.0 }
+    }
+};
+
 
 impl <Clock: TimeDaemon> Emulator<Clock> {
     pub fn wasm_init(&mut self) {
@@ -95,14 +382,24 @@ impl <Clock: TimeDaemon> Emulator<Clock> {
     }
 
     pub fn init(clock: Clock, target_sample_rate: f64) -> Self {
+        Self::init_with_power_on(clock, target_sample_rate, PowerOnFill::default())
+    }
+
+    /// Like `init`, with memory filled the way `power_on_fill` says.
+    pub fn init_with_power_on(clock: Clock, target_sample_rate: f64, power_on_fill: PowerOnFill) -> Self {
         let play_state = WasmInit;
 
         let mut bus = CpuBus::default();
+        let mut acp_bus = AcpBus::new(bus.aram.share());
+        fill_memory(power_on_fill, &mut bus, &mut acp_bus);
+        let video = VideoOutput::new(&bus.read_full_framebuffer()[..]);
         let mut cpu = W65C02S::new();
         cpu.step(&mut bus); // take one initial step, to get through the reset vector
         let acp = W65C02S::new();
 
         let blitter = Blitter::default();
+        let rom_hash = save_state::fnv1a64(bus.cartridge.image());
+        let rom_info = RomInfo::default().complete(bus.cartridge.kind());
 
         let last_cpu_tick_ms = clock.get_now_ms();
         let cpu_frequency_hz = 3_579_545.0; // Precise frequency
@@ -113,12 +410,27 @@ impl <Clock: TimeDaemon> Emulator<Clock> {
         Emulator {
             play_state,
             cpu_bus: bus,
-            acp_bus: AcpBus::default(),
+            acp_bus,
             cpu,
             acp,
             blitter,
+            video,
 
-            clock_cycles_to_vblank: 59659,
+            clock_cycles_to_vblank: CYCLES_PER_FRAME,
+            acp_cycle_accumulator: 0,
+            cycle_overshoot: 0,
+            power_on_fill,
+            rom_hash,
+            rom_info,
+            frame_count: 0,
+            rewind: None,
+            movie: None,
+            debugger: Debugger::default(),
+            symbols: SymbolTable::default(),
+            acp_symbols: SymbolTable::default(),
+            tracer: None,
+            uninit_callback: None,
+            save_storage: None,
             last_emu_tick: last_cpu_tick_ms,
             cpu_frequency_hz,
             cpu_ns_per_cycle,
@@ -149,55 +461,167 @@ impl <Clock: TimeDaemon> Emulator<Clock> {
         let elapsed_ns = elapsed_ms * 1000000.0;
         let mut remaining_cycles: i32 = (elapsed_ns / self.cpu_ns_per_cycle) as i32;
 
-        let mut acp_cycle_accumulator = 0;
-
+        let mut summary = RunSummary::default();
         while remaining_cycles > 0 {
-            if self.cpu.get_state() == AwaitingInterrupt {
-                self.wait_counter += 1;
-                // get cpu's current asm code
-            } else if self.wait_counter > 0 {
-                debug!("waited {} cycles", self.wait_counter);
-                self.wait_counter = 0;
+            remaining_cycles -= self.step(&mut summary);
+            if self.debugger.is_paused() {
+                self.play_state = Paused;
+                break;
             }
+        }
 
-            let cpu_cycles = self.cpu.step(&mut self.cpu_bus);
+        self.last_emu_tick = now_ms;
 
-            remaining_cycles -= cpu_cycles;
+        if !is_web && (now_ms - self.last_render_time) >= 16.67 {
+            debug!("time since last render: {}", now_ms - self.last_render_time);
+            self.last_render_time = now_ms;
+        }
+    }
 
-            acp_cycle_accumulator += cpu_cycles * 4;
+    /// Runs exactly `cycles` CPU cycles without consulting the clock, regardless of the play
+    /// state. Inputs set since the last call are applied first. Stops early if a breakpoint or
+    /// watchpoint is hit.
+    pub fn run_cycles(&mut self, cycles: u64) -> RunSummary {
+        self.process_inputs();
+        self.debugger.resume();
 
-            // pass aram to acp
-            if self.cpu_bus.system_control.acp_enabled() {
-                self.run_acp(&mut acp_cycle_accumulator);
-            }
+        let mut summary = RunSummary::default();
+        let target = cycles.saturating_sub(self.cycle_overshoot);
+        self.cycle_overshoot -= cycles - target;
+        while summary.cycles < target && !self.debugger.is_paused() {
+            self.step(&mut summary);
+        }
+        self.cycle_overshoot += summary.cycles.saturating_sub(target);
+        summary
+    }
 
-            // blit
-            for _ in 0..cpu_cycles {
-                self.blitter.cycle(&mut self.cpu_bus);
-            }
-            // TODO: instant blit option
+    /// Runs until the next vblank has happened, regardless of the play state. Stops early if a
+    /// breakpoint or watchpoint is hit.
+    pub fn run_until_vblank(&mut self) -> RunSummary {
+        self.process_inputs();
+        self.debugger.resume();
 
-            let blit_irq = self.blitter.irq_trigger;
-            if blit_irq {
-                debug!("blit irq");
-            }
-            self.cpu.set_irq(blit_irq);
+        let mut summary = RunSummary::default();
+        while summary.vblanks == 0 && !self.debugger.is_paused() {
+            self.step(&mut summary);
+        }
+        self.cycle_overshoot = 0;
+        summary
+    }
+
+    /// Executes a single instruction on `cpu` and pauses again. The other components keep
+    /// running alongside it as usual, so stepping the ACP may run several main CPU instructions
+    /// (or none at all, if the ACP is held in reset for a whole frame).
+    pub fn step_instruction(&mut self, cpu: CpuId) -> RunSummary {
+        self.debugger.resume();
+        self.debugger.stepping = Some(cpu);
 
-            self.clock_cycles_to_vblank -= cpu_cycles;
-            if self.clock_cycles_to_vblank <= 0 {
-                self.vblank();
+        let mut summary = RunSummary::default();
+        while !self.debugger.is_paused() && summary.cycles < CYCLES_PER_FRAME as u64 {
+            self.step(&mut summary);
+        }
+        self.debugger.stepping = None;
+        self.play_state = Paused;
+        summary
+    }
+
+    /// Runs one whole frame: applies pending inputs, then runs up to and including the next
+    /// vblank.
+    pub fn run_frame(&mut self) -> RunSummary {
+        self.run_until_vblank()
+    }
+
+    /// Executes one CPU instruction (or interrupt entry). The ACP and the blitter advance in
+    /// lockstep with each of its bus cycles, so they see its writes (and it sees theirs) on the
+    /// cycle they happen; the vblank countdown catches up afterwards. Returns the number of CPU
+    /// cycles taken.
+    fn step(&mut self, summary: &mut RunSummary) -> i32 {
+        let ctx = MainContext { cpu: &self.cpu, bus: &self.cpu_bus };
+        if self.debugger.check_breakpoints(CpuId::Main, self.cpu.get_state() == Running, &ctx) {
+            return 0;
+        }
+
+        if self.cpu.get_state() == AwaitingInterrupt {
+            if self.wait_counter == 0 {
+                let pc = self.cpu.get_pc();
+                debug!("waiting for interrupt at ${:04X}: {}", pc, disasm::decode(pc, |addr| self.cpu_bus.peek_byte(addr)));
             }
+            self.wait_counter += 1;
+        } else if self.wait_counter > 0 {
+            debug!("waited {} cycles", self.wait_counter);
+            self.wait_counter = 0;
         }
 
-        self.last_emu_tick = now_ms;
+        if self.tracer.is_some() {
+            self.trace(CpuId::Main);
+        }
 
-        if !is_web && (now_ms - self.last_render_time) >= 16.67 {
-            debug!("time since last render: {}", now_ms - self.last_render_time);
-            self.last_render_time = now_ms;
+        let pc = self.cpu.get_pc();
+        // the cpu is taken out while it runs, so its bus callbacks can borrow everything else
+        let mut cpu = core::mem::replace(&mut self.cpu, W65C02S::new());
+        let mut lockstep = Lockstep { emulator: self, summary, cycles: 0 };
+        let reported_cycles = cpu.step(&mut lockstep);
+        // cycles the instruction didn't spend on the bus still pass for everything else
+        while lockstep.cycles < reported_cycles {
+            lockstep.tick(&mut cpu);
+        }
+        let cpu_cycles = lockstep.cycles;
+        self.cpu = cpu;
+
+        if let Some(tracer) = &mut self.tracer {
+            tracer.advance(CpuId::Main, cpu_cycles);
         }
+        let hits = self.cpu_bus.watches.take_hits();
+        if !hits.is_empty() {
+            let ctx = MainContext { cpu: &self.cpu, bus: &self.cpu_bus };
+            self.debugger.check_watch_hits(CpuId::Main, hits, &ctx);
+        }
+        self.report_uninit(CpuId::Main, pc);
+        self.debugger.instruction_done(CpuId::Main);
+
+        self.clock_cycles_to_vblank -= cpu_cycles;
+        if self.clock_cycles_to_vblank <= 0 {
+            summary.vblanks += 1;
+            summary.nmi_fired |= self.cpu_bus.vblank_nmi_enabled();
+            self.vblank();
+        }
+
+        summary.cycles += cpu_cycles as u64;
+        cpu_cycles
+    }
+
+    /// Records what `cpu` is about to do into the tracer.
+    fn trace(&mut self, cpu: CpuId) {
+        let Some(mut tracer) = self.tracer.take() else { return };
+        if tracer.is_tracing(cpu) {
+            let cycle = tracer.cycles[cpu as usize];
+            let entry = match cpu {
+                CpuId::Main => TraceEntry::capture(cpu, &self.cpu, cycle, self.cpu_bus.bank_at(self.cpu.get_pc()), |addr| self.cpu_bus.peek_byte(addr)),
+                CpuId::Acp => TraceEntry::capture(cpu, &self.acp, cycle, None, |addr| self.acp_bus.read_byte(addr)),
+            };
+            if let Some(entry) = entry {
+                tracer.record(entry, |addr| self.label(cpu, addr));
+            }
+        }
+        self.tracer = Some(tracer);
     }
 
-    fn run_acp(&mut self, acp_cycle_accumulator: &mut i32) {
+    /// Hands the reads of never written memory that `cpu`'s instruction at `pc` made to the
+    /// detector. The main cpu's also include the blitter's fetches while it ran.
+    fn report_uninit(&mut self, cpu: CpuId, pc: u16) {
+        let hits = match cpu {
+            CpuId::Main => &mut self.cpu_bus.uninit_hits,
+            CpuId::Acp => &mut self.acp_bus.uninit_hits,
+        };
+        let Some(hits) = hits.as_mut().filter(|hits| !hits.is_empty()).map(core::mem::take) else { return };
+        if let Some(callback) = &mut self.uninit_callback {
+            for hit in hits {
+                callback(hit.at(pc));
+            }
+        }
+    }
+
+    fn run_acp(&mut self) {
         if self.cpu_bus.system_control.clear_acp_reset() {
             self.acp.reset();
         }
@@ -206,9 +630,31 @@ impl <Clock: TimeDaemon> Emulator<Clock> {
             self.acp.set_nmi(true);
         }
 
-        while *acp_cycle_accumulator > 0 {
+        // a pause by either cpu holds the ACP too, including one that happened earlier in the
+        // main cpu instruction this runs alongside
+        while self.acp_cycle_accumulator > 0 && !self.debugger.is_paused() {
+            let ctx = AcpContext { cpu: &self.acp, bus: &self.acp_bus };
+            if self.debugger.check_breakpoints(CpuId::Acp, self.acp.get_state() == Running, &ctx) {
+                break;
+            }
+
+            if self.tracer.is_some() {
+                self.trace(CpuId::Acp);
+            }
+
+            let pc = self.acp.get_pc();
             let acp_cycles = self.acp.step(&mut self.acp_bus);
-            *acp_cycle_accumulator -= acp_cycles;
+            if let Some(tracer) = &mut self.tracer {
+                tracer.advance(CpuId::Acp, acp_cycles);
+            }
+            let hits = self.acp_bus.watches.take_hits();
+            if !hits.is_empty() {
+                let ctx = AcpContext { cpu: &self.acp, bus: &self.acp_bus };
+                self.debugger.check_watch_hits(CpuId::Acp, hits, &ctx);
+            }
+            self.report_uninit(CpuId::Acp, pc);
+            self.debugger.instruction_done(CpuId::Acp);
+            self.acp_cycle_accumulator -= acp_cycles;
             self.acp_bus.irq_counter -= acp_cycles;
 
             // clear stuff ig
@@ -221,7 +667,7 @@ impl <Clock: TimeDaemon> Emulator<Clock> {
 
                 let sample_rate = self.cpu_frequency_hz / self.cpu_bus.system_control.sample_rate() as f64;
                 // if audio_out is none or mismatched sample rate
-                if self.audio_out.as_ref().map_or(true, |gta| gta.sample_rate != sample_rate) {
+                if self.audio_out.as_ref().is_none_or(|gta| gta.sample_rate != sample_rate) {
                     warn!("recreated audio stream with new sample rate: {:.3}Hz ({})", sample_rate, self.cpu_bus.system_control.sample_rate());
                     self.audio_out = Some(GameTankAudio::new(sample_rate, self.target_sample_rate));
                 }
@@ -242,66 +688,447 @@ impl <Clock: TimeDaemon> Emulator<Clock> {
     }
 
     fn vblank(&mut self) {
-        self.clock_cycles_to_vblank += 59659;
+        self.clock_cycles_to_vblank += CYCLES_PER_FRAME;
+        self.frame_count += 1;
+        self.video.vblank();
 
         if self.cpu_bus.vblank_nmi_enabled() {
             self.cpu.set_nmi(true);
             debug!("vblanked");
         }
+
+        if self.save_storage.is_some() && self.cpu_bus.cartridge.has_unsaved_changes() {
+            if let Err(e) = self.flush_save() {
+                error!("could not persist save data: {}", e);
+            }
+        }
+
+        if let Some(mut rewind) = self.rewind.take() {
+            if rewind.tick() {
+                rewind.push(self.frame_count, &self.save_state());
+            }
+            self.rewind = Some(rewind);
+        }
+
+        if let Some(movie) = &mut self.movie {
+            for (command, state) in movie.next_frame() {
+                self.input_state.insert(command, state).expect("shit's full dog ://");
+            }
+            self.process_inputs();
+            if self.movie.as_ref().is_some_and(|m| m.is_finished()) {
+                info!("movie playback finished after {} frames", self.movie.as_ref().map_or(0, |m| m.frame));
+                self.movie = None;
+            }
+        }
+    }
+
+    /// Decodes `count` instructions starting at `address` in `cpu`'s address space. Memory is
+    /// peeked, so this never triggers read side effects.
+    pub fn disassemble(&self, cpu: CpuId, address: u16, count: usize) -> Vec<Instruction> {
+        match cpu {
+            CpuId::Main => Disassembler::new(address, |addr| self.cpu_bus.peek_byte(addr)).take(count).collect(),
+            CpuId::Acp => Disassembler::new(address, |addr| self.acp_bus.read_byte(addr)).take(count).collect(),
+        }
+    }
+
+    /// Stops `cpu` before it executes the instruction at `address`, if `condition` (see
+    /// `Condition`) holds. Returns the breakpoint's id.
+    pub fn add_breakpoint(&mut self, cpu: CpuId, address: u16, condition: Option<&str>) -> Result<u32, ExprError> {
+        let condition = self.parse_condition(cpu, condition)?;
+        Ok(self.debugger.add_breakpoint(cpu, address, None, condition))
+    }
+
+    /// Like `add_breakpoint`, at the address of a symbol from `symbols`. For symbols in banked
+    /// rom, the breakpoint only triggers while the symbol's bank is mapped in.
+    pub fn add_symbol_breakpoint(&mut self, cpu: CpuId, name: &str, condition: Option<&str>) -> Result<u32, ExprError> {
+        let (address, bank) = self.symbols_for(cpu).address_of(name).ok_or_else(|| ExprError::NoSuchSymbol(name.into()))?;
+        let condition = self.parse_condition(cpu, condition)?;
+        Ok(self.debugger.add_breakpoint(cpu, address, bank, condition))
+    }
+
+    fn parse_condition(&self, cpu: CpuId, condition: Option<&str>) -> Result<Option<Condition>, ExprError> {
+        let symbols = self.symbols_for(cpu);
+        condition.map(|c| Condition::parse_with_symbols(c, &|name| symbols.address_of(name).map(|(address, _)| address))).transpose()
+    }
+
+    fn symbols_for(&self, cpu: CpuId) -> &SymbolTable {
+        match cpu {
+            CpuId::Main => &self.symbols,
+            CpuId::Acp => &self.acp_symbols,
+        }
+    }
+
+    /// Names `address` as `symbol+$offset`, taking the currently mapped bank into account.
+    pub fn label(&self, cpu: CpuId, address: u16) -> Option<Label<'_>> {
+        match cpu {
+            CpuId::Main => self.symbols.label(address, self.cpu_bus.bank_at(address)),
+            CpuId::Acp => self.acp_symbols.label(address, None),
+        }
+    }
+
+    /// The source file and line the code at `address` was built from.
+    pub fn source_line(&self, cpu: CpuId, address: u16) -> Option<(&str, u32)> {
+        match cpu {
+            CpuId::Main => self.symbols.source_line(address, self.cpu_bus.bank_at(address)),
+            CpuId::Acp => self.acp_symbols.source_line(address, None),
+        }
+    }
+
+    /// Writes a listing of `count` instructions from `address`, with labels, symbolic operands
+    /// and source lines where the symbol tables know them.
+    pub fn write_disassembly(&self, out: &mut impl Write, cpu: CpuId, address: u16, count: usize) -> core::fmt::Result {
+        for instruction in self.disassemble(cpu, address, count) {
+            if let Some(label) = self.label(cpu, instruction.address).filter(|l| l.offset == 0) {
+                writeln!(out, "{}:", label)?;
+            }
+            write!(out, "  ${:04X}  ", instruction.address)?;
+            instruction.write_to(out, |address| self.label(cpu, address))?;
+            if let Some((file, line)) = self.source_line(cpu, instruction.address) {
+                write!(out, "  ; {}:{}", file, line)?;
+            }
+            writeln!(out)?;
+        }
+        Ok(())
+    }
+
+    /// Stops after any instruction on `cpu` that accesses an address in `start..=end`, if
+    /// `condition` holds at that point. Instruction fetches don't count as reads. Returns the
+    /// watchpoint's id.
+    pub fn add_watchpoint(&mut self, cpu: CpuId, start: u16, end: u16, kind: WatchKind, condition: Option<&str>) -> Result<u32, ExprError> {
+        let condition = self.parse_condition(cpu, condition)?;
+        let id = self.debugger.next_id();
+        self.debugger.add_watch_condition(id, cpu, condition);
+        match cpu {
+            CpuId::Main => { self.cpu_bus.watches.add(id, start, end, kind) }
+            CpuId::Acp => { self.acp_bus.watches.add(id, start, end, kind) }
+        }
+        Ok(id)
+    }
+
+    /// Turns on the uninitialized memory detector, which calls `callback` for every read of a
+    /// byte of RAM, VRAM or audio RAM that nothing wrote since power on: by either cpu, or by
+    /// the blitter fetching source pixels. `None` turns it off. Loading a save state, or
+    /// rewinding, counts all memory as written.
+    pub fn set_uninit_detector(&mut self, callback: Option<Box<dyn FnMut(UninitRead) + Send>>) {
+        let hits = callback.as_ref().map(|_| Vec::new());
+        self.cpu_bus.uninit_hits = hits.clone();
+        self.acp_bus.uninit_hits = hits;
+        self.uninit_callback = callback;
+    }
+
+    /// Removes a breakpoint or watchpoint. Returns false if there was none with this id.
+    pub fn remove_breakpoint(&mut self, id: u32) -> bool {
+        match self.debugger.remove(id) {
+            Some(CpuId::Main) => { self.cpu_bus.watches.remove(id); true }
+            Some(CpuId::Acp) => { self.acp_bus.watches.remove(id); true }
+            None => false,
+        }
+    }
+
+    /// Continues after a breakpoint, watchpoint or single step.
+    pub fn resume(&mut self) {
+        self.debugger.resume();
+        self.play_state = Playing;
+    }
+
+    /// Starts recording inputs into a movie. With `from_current_state` the movie starts from a
+    /// save state of the running machine, otherwise the machine is hard reset first so the
+    /// movie starts from power-on.
+    pub fn start_recording(&mut self, from_current_state: bool) {
+        let start_state = if from_current_state {
+            Some(self.save_state())
+        } else {
+            self.hard_reset();
+            None
+        };
+        self.input_state.clear();
+        self.movie = Some(MovieSession::recording(Movie::new(self.rom_hash, start_state)));
+    }
+
+    /// Stops recording and returns the finished movie.
+    pub fn stop_recording(&mut self) -> Option<Movie> {
+        match self.movie.take()? {
+            MovieSession { mode: MovieMode::Recording, movie, .. } => Some(movie),
+            playback => {
+                self.movie = Some(playback);
+                None
+            }
+        }
+    }
+
+    /// Restores the movie's starting point and plays its inputs back, one frame boundary at a
+    /// time. Live inputs other than play/pause are ignored until playback finishes.
+    pub fn start_playback(&mut self, movie: Movie) -> Result<(), StateError> {
+        if movie.rom_hash != self.rom_hash {
+            return Err(StateError::RomMismatch { expected: self.rom_hash, found: movie.rom_hash });
+        }
+        match &movie.start_state {
+            Some(state) => { self.load_state(state)? }
+            None => { self.hard_reset() }
+        }
+        self.input_state.clear();
+        self.movie = Some(MovieSession::playback(movie));
+        Ok(())
+    }
+
+    pub fn stop_movie(&mut self) {
+        self.movie = None;
+    }
+
+    /// Starts recording a snapshot every `interval` vblanks, keeping at most `memory_budget`
+    /// bytes of history.
+    pub fn enable_rewind(&mut self, interval: u32, memory_budget: usize) {
+        self.rewind = Some(RewindBuffer::new(interval, memory_budget));
+    }
+
+    pub fn disable_rewind(&mut self) {
+        self.rewind = None;
+    }
+
+    /// Goes back at least `frames` vblanks, to the newest recorded snapshot at or before that
+    /// point. Returns the number of frames actually rewound, or `None` if there's no history.
+    pub fn rewind(&mut self, frames: u64) -> Option<u64> {
+        let (frame, state) = self.rewind.as_mut()?.rewind(self.frame_count, frames)?;
+        if let Err(e) = self.load_state(&state) {
+            error!("failed to restore rewind snapshot: {}", e);
+            self.rewind.as_mut()?.clear();
+            return None;
+        }
+        let rewound = self.frame_count.saturating_sub(frame);
+        self.frame_count = frame;
+        Some(rewound)
     }
 
     pub fn set_input_state(&mut self, input_command: InputCommand, state: KeyState) {
+        if input_command != PlayPause {
+            match &mut self.movie {
+                Some(movie) if movie.mode == MovieMode::Recording => {
+                    // applied at the next frame boundary, exactly like playback will
+                    movie.record(input_command, state);
+                    return;
+                }
+                Some(_) => {
+                    debug!("ignoring {:?} during movie playback", input_command);
+                    return;
+                }
+                None => {}
+            }
+        }
         self.input_state.insert(input_command, state).expect("shit's full dog ://");
     }
 
     fn process_inputs(&mut self) {
         let keys: Vec<_> = self.input_state.keys().cloned().collect();  // Clone keys to avoid borrowing conflicts
 
-        if keys.len() > 0 && self.play_state == WasmInit {
+        if !keys.is_empty() && self.play_state == WasmInit {
             self.play_state = Playing;
         }
 
         for key in &keys {
             match key {
-                Controller1(button) => { self.set_gamepad_input(0, &key, &button); }
-                Controller2(button) => { self.set_gamepad_input(1, &key, &button); }
+                Controller1(button) => { self.set_gamepad_input(0, key, button); }
+                Controller2(button) => { self.set_gamepad_input(1, key, button); }
                 PlayPause => {
                     if self.input_state[key] == JustReleased {
                         match self.play_state {
-                            Paused => { self.play_state = Playing; }
+                            Paused => { self.resume(); }
                             Playing => { self.play_state = Paused; }
                             WasmInit => { self.play_state = Playing; }
                         }
                     }
                 }
                 SoftReset => {
-                    self.cpu.reset();
+                    if self.input_state[key] == JustPressed {
+                        self.cpu.reset();
+                    }
                 }
                 HardReset => {
-                    // hard reset reinitializes memory/cpus
-                    let cart = self.cpu_bus.cartridge.clone();
-                    self.cpu_bus = CpuBus::default();
-                    self.cpu_bus.cartridge = cart;
-                    self.cpu = W65C02S::new();
-                    self.cpu.step(&mut self.cpu_bus); // take one initial step, to get through the reset vector
-                    self.acp = W65C02S::new();
-                    self.blitter = Blitter::default();
+                    if self.input_state[key] == JustPressed {
+                        self.hard_reset();
+                    }
                 }
             }
             self.input_state.insert(*key, self.input_state[key].update()).expect("shit's full dog ://");
         }
     }
+    /// Reinitializes memory and both cpus, keeping the cartridge, any watchpoints and the open
+    /// bus policies.
+    pub fn hard_reset(&mut self) {
+        let mut bus = CpuBus::default();
+        core::mem::swap(&mut bus.cartridge, &mut self.cpu_bus.cartridge);
+        core::mem::swap(&mut bus.watches, &mut self.cpu_bus.watches);
+        bus.open_bus = self.cpu_bus.open_bus;
+        bus.uninit_hits = self.cpu_bus.uninit_hits.as_ref().map(|_| Vec::new());
+        let mut acp_bus = AcpBus::new(bus.aram.share());
+        core::mem::swap(&mut acp_bus.watches, &mut self.acp_bus.watches);
+        acp_bus.uninit_hits = self.acp_bus.uninit_hits.as_ref().map(|_| Vec::new());
+        fill_memory(self.power_on_fill, &mut bus, &mut acp_bus);
+        self.cpu_bus = bus;
+        self.acp_bus = acp_bus;
+        self.cpu = W65C02S::new();
+        self.cpu.step(&mut self.cpu_bus); // take one initial step, to get through the reset vector
+        self.cpu_bus.watches.take_hits();
+        self.acp = W65C02S::new();
+        self.acp_cycle_accumulator = 0;
+        self.blitter = Blitter::default();
+        self.video = VideoOutput::new(&self.cpu_bus.read_full_framebuffer()[..]);
+        self.clock_cycles_to_vblank = CYCLES_PER_FRAME;
+    }
+
     fn set_gamepad_input(&mut self, gamepad: usize, key: &InputCommand, button: &ControllerButton) {
         let gamepad = &mut self.cpu_bus.system_control.gamepads[gamepad];
         match button {
-            Up =>     { gamepad.up    = self.input_state[&key].is_pressed(); }
-            Down =>   { gamepad.down  = self.input_state[&key].is_pressed(); }
-            Left =>   { gamepad.left  = self.input_state[&key].is_pressed(); }
-            Right =>  { gamepad.right = self.input_state[&key].is_pressed(); }
-            B =>      { gamepad.b     = self.input_state[&key].is_pressed(); }
-            A =>      { gamepad.a     = self.input_state[&key].is_pressed(); }
-            Start =>  { gamepad.start = self.input_state[&key].is_pressed(); }
-            C =>      { gamepad.c     = self.input_state[&key].is_pressed(); }
+            Up =>     { gamepad.up    = self.input_state[key].is_pressed(); }
+            Down =>   { gamepad.down  = self.input_state[key].is_pressed(); }
+            Left =>   { gamepad.left  = self.input_state[key].is_pressed(); }
+            Right =>  { gamepad.right = self.input_state[key].is_pressed(); }
+            B =>      { gamepad.b     = self.input_state[key].is_pressed(); }
+            A =>      { gamepad.a     = self.input_state[key].is_pressed(); }
+            Start =>  { gamepad.start = self.input_state[key].is_pressed(); }
+            C =>      { gamepad.c     = self.input_state[key].is_pressed(); }
         }
     }
-}
\ No newline at end of file
+}
+
+/// Fills every memory in the machine as it is at power on.
+fn fill_memory(power_on_fill: PowerOnFill, cpu_bus: &mut CpuBus, acp_bus: &mut AcpBus) {
+    let mut filler = power_on_fill.filler();
+    for bank in cpu_bus.ram_banks.iter_mut() {
+        filler.fill(bank);
+    }
+    for bank in cpu_bus.vram_banks.iter_mut() {
+        filler.fill(bank);
+    }
+    // the standard fill leaves the framebuffers as `CpuBus::default` sets them up
+    if power_on_fill != PowerOnFill::Standard {
+        for fb in &cpu_bus.framebuffers {
+            filler.fill(fb.borrow_mut().as_mut_slice());
+        }
+    }
+    let mut aram = [0; ARAM_SIZE];
+    filler.fill(&mut aram);
+    acp_bus.aram.power_on(&aram);
+}
+
+/// The main cpu's view of the system while it executes an instruction. Every bus cycle it
+/// performs is followed by a cycle of everything else: four ACP cycles, one blitter cycle and
+/// one VIA cycle.
+struct Lockstep<'a, Clock: TimeDaemon> {
+    emulator: &'a mut Emulator<Clock>,
+    summary: &'a mut RunSummary,
+    cycles: i32,
+}
+
+impl<Clock: TimeDaemon> Lockstep<'_, Clock> {
+    fn tick(&mut self, cpu: &mut W65C02S) {
+        self.cycles += 1;
+        let emulator = &mut *self.emulator;
+
+        // the ACP only has cycles to catch up on while it's running, or re-enabling it would
+        // run everything it missed in one burst
+        if emulator.cpu_bus.system_control.acp_enabled() {
+            emulator.acp_cycle_accumulator += 4;
+            emulator.run_acp();
+        }
+
+        if emulator.blitter.cycle(&mut emulator.cpu_bus) {
+            self.summary.blits_completed += 1;
+        }
+        // TODO: instant blit option
+
+        emulator.cpu_bus.cartridge.tick();
+
+        let via = &mut emulator.cpu_bus.system_control.via;
+        via.tick();
+        cpu.set_irq(emulator.blitter.irq_trigger || via.irq());
+
+        let frame_cycle = (CYCLES_PER_FRAME - emulator.clock_cycles_to_vblank + self.cycles).max(0) as u32;
+        if emulator.video.due(frame_cycle) {
+            emulator.video.catch_up(frame_cycle, &emulator.cpu_bus.read_full_framebuffer()[..]);
+        }
+    }
+}
+
+impl<Clock: TimeDaemon> System for Lockstep<'_, Clock> {
+    fn read_opcode(&mut self, cpu: &mut W65C02S, addr: u16) -> u8 {
+        let data = self.emulator.cpu_bus.read_opcode(cpu, addr);
+        self.tick(cpu);
+        data
+    }
+
+    fn read(&mut self, cpu: &mut W65C02S, addr: u16) -> u8 {
+        let data = self.emulator.cpu_bus.read(cpu, addr);
+        self.tick(cpu);
+        data
+    }
+
+    fn read_locked(&mut self, cpu: &mut W65C02S, addr: u16) -> u8 {
+        let data = self.emulator.cpu_bus.read_locked(cpu, addr);
+        self.tick(cpu);
+        data
+    }
+
+    fn read_locked_spurious(&mut self, cpu: &mut W65C02S, addr: u16) {
+        self.emulator.cpu_bus.read_locked_spurious(cpu, addr);
+        self.tick(cpu);
+    }
+
+    fn read_vector(&mut self, cpu: &mut W65C02S, addr: u16) -> u8 {
+        let data = self.emulator.cpu_bus.read_vector(cpu, addr);
+        self.tick(cpu);
+        data
+    }
+
+    fn write(&mut self, cpu: &mut W65C02S, addr: u16, data: u8) {
+        self.emulator.cpu_bus.write(cpu, addr, data);
+        self.tick(cpu);
+    }
+
+    fn write_stack(&mut self, cpu: &mut W65C02S, addr: u16, data: u8) {
+        self.emulator.cpu_bus.write_stack(cpu, addr, data);
+        self.tick(cpu);
+    }
+
+    fn write_locked(&mut self, cpu: &mut W65C02S, addr: u16, data: u8) {
+        self.emulator.cpu_bus.write_locked(cpu, addr, data);
+        self.tick(cpu);
+    }
+
+    fn read_opcode_spurious(&mut self, cpu: &mut W65C02S, addr: u16) {
+        self.emulator.cpu_bus.read_opcode_spurious(cpu, addr);
+        self.tick(cpu);
+    }
+
+    fn read_operand(&mut self, cpu: &mut W65C02S, addr: u16) -> u8 {
+        let data = self.emulator.cpu_bus.read_operand(cpu, addr);
+        self.tick(cpu);
+        data
+    }
+
+    fn read_operand_spurious(&mut self, cpu: &mut W65C02S, addr: u16) {
+        self.emulator.cpu_bus.read_operand_spurious(cpu, addr);
+        self.tick(cpu);
+    }
+
+    fn read_pointer(&mut self, cpu: &mut W65C02S, addr: u16) -> u8 {
+        let data = self.emulator.cpu_bus.read_pointer(cpu, addr);
+        self.tick(cpu);
+        data
+    }
+
+    fn read_stack(&mut self, cpu: &mut W65C02S, addr: u16) -> u8 {
+        let data = self.emulator.cpu_bus.read_stack(cpu, addr);
+        self.tick(cpu);
+        data
+    }
+
+    fn read_stack_spurious(&mut self, cpu: &mut W65C02S, addr: u16) {
+        self.emulator.cpu_bus.read_stack_spurious(cpu, addr);
+        self.tick(cpu);
+    }
+
+    fn read_spurious(&mut self, cpu: &mut W65C02S, addr: u16) {
+        self.emulator.cpu_bus.read_spurious(cpu, addr);
+        self.tick(cpu);
+    }
+}
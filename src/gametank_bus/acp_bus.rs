@@ -1,24 +1,136 @@
 #![allow(dead_code, unused_variables, unused_imports, internal_features, static_mut_refs)]
 
 use alloc::boxed::Box;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::fmt::{Debug, Formatter};
+use core::sync::atomic::{AtomicU64, AtomicU8, Ordering};
 use log::{error};
 use gte_w65c02s::{System, W65C02S};
 use crate::gametank_bus::Bus;
+use crate::debugger::{Access, WatchList};
+use crate::save_state::{Snapshot, StateError, StateReader, StateWriter};
+use crate::uninit::{UninitHit, UninitMemory, UninitReader};
 
-pub static mut ARAM: &'static mut [u8; 0x1000]  = &mut [0; 0x1000];
+pub const ARAM_SIZE: usize = 0x1000;
 
-#[derive(Default, Debug)]
+struct AramCells {
+    bytes: [AtomicU8; ARAM_SIZE],
+    // one bit per byte, set once it's been written since power on
+    written: [AtomicU64; ARAM_SIZE / 64],
+}
+
+/// The ACP's 4K of RAM, which the main cpu also sees at $3000. Every emulator owns its own,
+/// and both of its buses hold a handle to it. Addresses wrap to 4K.
+pub struct AudioRam(Arc<AramCells>);
+
+impl AudioRam {
+    pub fn new() -> Self {
+        Self(Arc::new(AramCells {
+            bytes: [const { AtomicU8::new(0) }; ARAM_SIZE],
+            written: [const { AtomicU64::new(0) }; ARAM_SIZE / 64],
+        }))
+    }
+
+    /// Another handle to the same memory.
+    pub fn share(&self) -> Self {
+        Self(self.0.clone())
+    }
+
+    #[inline(always)]
+    pub fn read(&self, address: u16) -> u8 {
+        self.0.bytes[address as usize & (ARAM_SIZE - 1)].load(Ordering::Relaxed)
+    }
+
+    #[inline(always)]
+    pub fn write(&self, address: u16, data: u8) {
+        let index = address as usize & (ARAM_SIZE - 1);
+        self.0.bytes[index].store(data, Ordering::Relaxed);
+        let written = &self.0.written[index / 64];
+        written.store(written.load(Ordering::Relaxed) | 1 << (index % 64), Ordering::Relaxed);
+    }
+
+    /// Whether the byte at `address` has been written since power on.
+    pub fn is_written(&self, address: u16) -> bool {
+        let index = address as usize & (ARAM_SIZE - 1);
+        self.0.written[index / 64].load(Ordering::Relaxed) & 1 << (index % 64) != 0
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.0.bytes.iter().map(|b| b.load(Ordering::Relaxed)).collect()
+    }
+
+    /// Replaces the contents without counting as writes, as when restoring a snapshot.
+    pub fn set_bytes(&self, bytes: &[u8]) {
+        for (cell, &b) in self.0.bytes.iter().zip(bytes) {
+            cell.store(b, Ordering::Relaxed);
+        }
+    }
+
+    /// Sets the contents at power on, forgetting every earlier write.
+    pub(crate) fn power_on(&self, bytes: &[u8]) {
+        self.set_bytes(bytes);
+        for written in &self.0.written {
+            written.store(0, Ordering::Relaxed);
+        }
+    }
+
+    /// Counts every byte as written.
+    pub(crate) fn mark_written(&self) {
+        for written in &self.0.written {
+            written.store(!0, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Clones are separate copies of the memory, written bits included. Use `share` for another
+/// handle to the same memory.
+impl Clone for AudioRam {
+    fn clone(&self) -> Self {
+        let copy = Self::new();
+        copy.set_bytes(&self.to_bytes());
+        for (to, from) in copy.0.written.iter().zip(&self.0.written) {
+            to.store(from.load(Ordering::Relaxed), Ordering::Relaxed);
+        }
+        copy
+    }
+}
+
+impl Default for AudioRam {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Debug for AudioRam {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.write_str("AudioRam")
+    }
+}
+
+#[derive(Clone, Default, Debug)]
 pub struct AcpBus {
     cycles: u8,
     pub irq_counter: i32,
 
     pub sample: u8,
+
+    pub aram: AudioRam,
+
+    pub watches: WatchList,
+    // reads of never written bytes, collected while the detector is on
+    pub(crate) uninit_hits: Option<Vec<UninitHit>>,
 }
 
 impl AcpBus {
+    /// A bus for an ACP that shares `aram` with the main cpu.
+    pub fn new(aram: AudioRam) -> Self {
+        Self { aram, ..Default::default() }
+    }
+
     #[inline(always)]
     pub(crate) fn write_byte(&mut self, address: u16, data: u8) {
-        unsafe { *ARAM.get_unchecked_mut((address & 0x0FFF) as usize) = data; }
+        self.aram.write(address, data);
         match address {
             0x8000..=0xFFFF => {
                 self.sample = data;
@@ -29,18 +141,96 @@ impl AcpBus {
 
     #[inline(always)]
     pub(crate) fn read_byte(&self, address: u16) -> u8 {
-        unsafe { *ARAM.get_unchecked((address as usize) & 0x0FFF) }
+        self.aram.read(address)
+    }
+
+    #[inline(always)]
+    fn check_uninit(&mut self, address: u16) {
+        if let Some(hits) = &mut self.uninit_hits {
+            if !self.aram.is_written(address) {
+                let offset = address as usize & (ARAM_SIZE - 1);
+                hits.push(UninitHit { reader: UninitReader::Acp, address, memory: UninitMemory::Aram, offset });
+            }
+        }
+    }
+
+    fn read_watched(&mut self, address: u16, rmw: bool) -> u8 {
+        let data = self.read_byte(address);
+        self.watches.check(address, Access::Read, data, rmw);
+        data
     }
 }
 
 impl System for AcpBus {
     #[inline(always)]
-    fn read(&mut self, _: &mut W65C02S, addr: u16) -> u8 {
+    fn read_opcode(&mut self, _: &mut W65C02S, addr: u16) -> u8 {
+        self.check_uninit(addr);
+        self.read_byte(addr)
+    }
+
+    #[inline(always)]
+    fn read_operand(&mut self, _: &mut W65C02S, addr: u16) -> u8 {
+        self.check_uninit(addr);
         self.read_byte(addr)
     }
 
+    #[inline(always)]
+    fn read(&mut self, _: &mut W65C02S, addr: u16) -> u8 {
+        self.check_uninit(addr);
+        self.read_watched(addr, false)
+    }
+
+    #[inline(always)]
+    fn read_locked(&mut self, _: &mut W65C02S, addr: u16) -> u8 {
+        self.check_uninit(addr);
+        self.read_watched(addr, true)
+    }
+
+    // dummy reads don't use the value, so they aren't reads of uninitialized memory
+    fn read_opcode_spurious(&mut self, _: &mut W65C02S, addr: u16) {
+        self.read_byte(addr);
+    }
+
+    fn read_operand_spurious(&mut self, _: &mut W65C02S, addr: u16) {
+        self.read_watched(addr, false);
+    }
+
+    fn read_stack_spurious(&mut self, _: &mut W65C02S, addr: u16) {
+        self.read_watched(addr, false);
+    }
+
+    fn read_locked_spurious(&mut self, _: &mut W65C02S, addr: u16) {
+        self.read_watched(addr, true);
+    }
+
+    fn read_spurious(&mut self, _: &mut W65C02S, addr: u16) {
+        self.read_watched(addr, false);
+    }
+
     #[inline(always)]
     fn write(&mut self, _: &mut W65C02S, addr: u16, data: u8) {
+        self.watches.check(addr, Access::Write, data, false);
         self.write_byte(addr, data);
     }
-}
\ No newline at end of file
+
+    #[inline(always)]
+    fn write_locked(&mut self, _: &mut W65C02S, addr: u16, data: u8) {
+        self.watches.check(addr, Access::Write, data, true);
+        self.write_byte(addr, data);
+    }
+}
+
+impl Snapshot for AcpBus {
+    fn save(&self, out: &mut StateWriter) {
+        out.put_u8(self.cycles);
+        out.put_i32(self.irq_counter);
+        out.put_u8(self.sample);
+    }
+
+    fn load(&mut self, input: &mut StateReader) -> Result<(), StateError> {
+        self.cycles = input.get_u8()?;
+        self.irq_counter = input.get_i32()?;
+        self.sample = input.get_u8()?;
+        Ok(())
+    }
+}
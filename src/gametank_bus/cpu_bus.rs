@@ -1,19 +1,56 @@
 use alloc::boxed::Box;
+use alloc::vec::Vec;
 use core::cell::Ref;
 use log::{debug, warn};
 use gte_w65c02s::{System, W65C02S};
 use crate::cartridges::cart2m::Cartridge2M;
 use crate::cartridges::CartridgeType;
+use crate::debugger::{Access, WatchList};
 use crate::gametank_bus::Bus;
 use crate::gametank_bus::reg_system_control::*;
-use crate::gametank_bus::ARAM;
-use crate::gametank_bus::cpu_bus::ByteDecorator::{AudioRam, CpuStack, SystemRam, Unreadable, Vram, ZeroPage};
+use crate::gametank_bus::via::*;
+use crate::gametank_bus::AudioRam;
+use crate::gametank_bus::cpu_bus::ByteDecorator::{CpuStack, SystemRam, Unreadable, Vram, ZeroPage};
 use crate::gametank_bus::reg_blitter::{BlitStart, BlitterRegisters};
 use crate::gametank_bus::reg_etc::{new_framebuffer, BankingRegister, BlitterFlags, FrameBuffer, GraphicsMemoryMap, SharedFrameBuffer};
 use crate::gametank_bus::reg_system_control::*;
 use crate::inputs::GamePad;
+use crate::save_state::{Snapshot, StateError, StateReader, StateWriter};
+use crate::uninit::{UninitHit, UninitMemory, UninitReader, WrittenBits};
 
-const CURRENT_GAME: &[u8] = include_bytes!("../cubicle.gtr");
+#[cfg(feature = "demo-rom")]
+const DEMO_GAME: &[u8] = include_bytes!("../cubicle.gtr");
+
+/// What's in the cartridge slot at power on: the demo game if it's built in, otherwise nothing.
+fn default_cartridge() -> CartridgeType {
+    #[cfg(feature = "demo-rom")]
+    return CartridgeType::from_slice(DEMO_GAME).expect("the built in rom is valid");
+    #[cfg(not(feature = "demo-rom"))]
+    return CartridgeType::Empty;
+}
+
+/// What a read returns when no device drives the data bus.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum OpenBusPolicy {
+    /// Whatever was last on the bus, which the bus capacitance holds on hardware.
+    #[default]
+    LastValue,
+    /// Always the same value, for software that only runs on emulators that return one.
+    Fixed(u8),
+}
+
+/// The open bus policy of every region that can't be read.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct OpenBusTable {
+    /// The write-only system control registers, $2000-$2007.
+    pub system_control: OpenBusPolicy,
+    /// The blitter registers, while they're mapped at $4000-$7FFF.
+    pub blitter: OpenBusPolicy,
+    /// $8000-$FFFF with the cartridge slot empty.
+    pub cartridge: OpenBusPolicy,
+    /// Everything nothing is mapped at: $200A-$27FF and $2810-$2FFF.
+    pub unmapped: OpenBusPolicy,
+}
 
 #[derive(Copy, Clone, Debug)]
 pub enum ByteDecorator {
@@ -30,7 +67,7 @@ pub enum ByteDecorator {
     Unreadable(u8),
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct CpuBus {
     cycles: u8,
     pub system_control: SystemControl,
@@ -43,19 +80,32 @@ pub struct CpuBus {
 
     pub vram_quad_written: [bool; 32],
 
-    // pub aram: Option<ARAM>,
+    /// Shared with the ACP's bus, see `AcpBus::new`.
+    pub aram: AudioRam,
     pub cartridge: CartridgeType,
+
+    /// The last value driven on the data bus, which reads of addresses nothing answers return.
+    pub data_bus: u8,
+    pub open_bus: OpenBusTable,
+
+    // which bytes of RAM and VRAM were written since power on, and the reads of any that
+    // weren't, collected while the detector is on
+    pub(crate) ram_written: WrittenBits,
+    pub(crate) vram_written: WrittenBits,
+    pub(crate) uninit_hits: Option<Vec<UninitHit>>,
+
+    pub watches: WatchList,
 }
 
 impl Default for CpuBus {
     fn default() -> Self {
-        let bus = Self {
+        Self {
             cycles: 0,
             system_control: SystemControl {
                 reset_acp: 0,
                 nmi_acp: 0,
                 banking_register: BankingRegister(0),
-                via_regs: [0; 16],
+                via: Via::default(),
                 audio_enable_sample_rate: 0,
                 dma_flags: BlitterFlags(0b0111_1111),
                 gamepads: [GamePad::default(), GamePad::default()]
@@ -71,17 +121,21 @@ impl Default for CpuBus {
                     write: 0,
                     addressed: false,
                 },
-                color: 0b101_00_000, // offwhite
+                color: 0b1010_0000, // offwhite (hue 101, saturation 00, luma 000)
             },
             ram_banks: Box::new([[0; 0x2000]; 4]),
             framebuffers: [new_framebuffer(0x00), new_framebuffer(0xFF)],
             vram_banks: Box::new([[0; 256*256]; 8]),
-            cartridge: CartridgeType::from_slice(CURRENT_GAME),
-            // aram: Some(Box::new([0; 0x1000])),
+            cartridge: default_cartridge(),
+            data_bus: 0,
+            open_bus: OpenBusTable::default(),
+            ram_written: WrittenBits::new(4 * 0x2000),
+            vram_written: WrittenBits::new(8 * 256*256),
+            uninit_hits: None,
+            aram: AudioRam::new(),
             vram_quad_written: [false; 32],
-        };
-
-        bus
+            watches: WatchList::default(),
+        }
     }
 }
 
@@ -91,11 +145,10 @@ impl CpuBus {
         self.framebuffers[fb].borrow()
     }
 
-    fn update_flash_shift_register(&mut self, next_val: u8) {
+    fn update_flash_shift_register(&mut self, old_val: u8, next_val: u8) {
         match &mut self.cartridge {
             CartridgeType::Cart2m(cartridge) => {
                 // For now, assuming that if we're using Flash2M hardware, we're behaving ourselves
-                let old_val = self.system_control.via_regs[VIA_IORA]; // Get the previous value from the VIA
                 let rising_bits = next_val & !old_val;
 
                 if rising_bits & VIA_SPI_BIT_CLK != 0 {
@@ -104,11 +157,6 @@ impl CpuBus {
                     cartridge.bank_shifter |= ((old_val & VIA_SPI_BIT_MOSI) != 0) as u8; // Set the last bit based on MOSI
                 } else if rising_bits & VIA_SPI_BIT_CS != 0 {
                     // Flash cart CS is connected to latch clock
-                    if (cartridge.bank_mask ^ cartridge.bank_shifter as u16) & 0x80 != 0 {
-                        // TODO: support saving
-                        // self.save_nvram(); // Assuming this is defined elsewhere or is a method within CpuBus
-                        warn!("Saving is not yet supported");
-                    }
                     cartridge.bank_mask = cartridge.bank_shifter as u16; // Update the bank mask
                     debug!("Flash bank mask set to 0x{:x}", cartridge.bank_mask);
                 }
@@ -118,10 +166,13 @@ impl CpuBus {
     }
 
     pub fn write_byte(&mut self, address: u16, data: u8) {
+        self.data_bus = data;
         match address {
             // system RAM
             0x0000..=0x1FFF => {
-                self.ram_banks[self.system_control.get_ram_bank()][address as usize] = data;
+                let bank = self.system_control.get_ram_bank();
+                self.ram_banks[bank][address as usize] = data;
+                self.ram_written.set(bank * 0x2000 + address as usize);
                 // println!("${:04X}={:02X}", address, data);
             }
 
@@ -133,19 +184,18 @@ impl CpuBus {
 
             // versatile interface adapter (GPIO, timers)
             0x2800..=0x280F => {
-                let register = (address & 0xF) as usize;
-                match (address & 0xF) as usize {
-                    VIA_IORA => {
-                        self.update_flash_shift_register(data);
-                    }
-                    _ => {}
+                // the flash cart listens to the port A pins, which DDRA changes can move too
+                let old_pins = self.system_control.via.port_a();
+                self.system_control.via.write((address & 0xF) as usize, data);
+                let new_pins = self.system_control.via.port_a();
+                if new_pins != old_pins {
+                    self.update_flash_shift_register(old_pins, new_pins);
                 }
-                self.system_control.via_regs[register] = data;
             }
 
             // audio RAM
-            0x3000..=0x3FFF => unsafe {
-                ARAM[(address - 0x3000) as usize] = data;
+            0x3000..=0x3FFF => {
+                self.aram.write(address - 0x3000, data);
             }
 
             // VRAM/Framebuffer/Blitter
@@ -159,6 +209,7 @@ impl CpuBus {
                         let vram_page = self.system_control.banking_register.vram_page() as usize;
                         let quadrant = self.blitter.vram_quadrant();
                         self.vram_banks[vram_page][address as usize - 0x4000 + quadrant*(128*128)] = data;
+                        self.vram_written.set(vram_page * 256*256 + address as usize - 0x4000 + quadrant*(128*128));
                         self.vram_quad_written[quadrant + vram_page * 4] = true;
                     }
                     GraphicsMemoryMap::BlitterRegisters => {
@@ -178,26 +229,30 @@ impl CpuBus {
     }
 
     pub fn read_byte(&mut self, address: u16) -> u8 {
-        match address {
+        let data = match address {
             // system RAM
             0x0000..=0x1FFF => {
-                return self.ram_banks[self.system_control.get_ram_bank()][address as usize];
+                self.ram_banks[self.system_control.get_ram_bank()][address as usize]
             }
 
-            // system control registers
-            0x2000..=0x2009 => {
-                return self.system_control.read_byte(address);
+            // gamepads, the only readable system control registers
+            0x2008..=0x2009 => {
+                self.system_control.read_byte(address)
+            }
+
+            0x2000..=0x2007 => {
+                self.system_control.read_byte(address);
+                self.open_bus_value(self.open_bus.system_control)
             }
 
             // versatile interface adapter (GPIO, timers)
             0x2800..=0x280F => {
-                let register = (address & 0xF) as usize;
-                return self.system_control.via_regs[register]
+                self.system_control.via.read((address & 0xF) as usize)
             }
 
             // audio RAM
-            0x3000..=0x3FFF => unsafe {
-                return ARAM[(address - 0x3000) as usize];
+            0x3000..=0x3FFF => {
+                self.aram.read(address - 0x3000)
             }
 
             // VRAM/Framebuffer/Blitter
@@ -205,28 +260,92 @@ impl CpuBus {
                 match self.system_control.get_graphics_memory_map() {
                     GraphicsMemoryMap::FrameBuffer => {
                         let fb = self.system_control.banking_register.framebuffer() as usize;
-                        return self.framebuffers[fb].borrow()[address as usize - 0x4000];
+                        self.framebuffers[fb].borrow()[address as usize - 0x4000]
                     }
                     GraphicsMemoryMap::VRAM => {
                         let vram_page = self.system_control.banking_register.vram_page() as usize;
                         let quadrant = self.blitter.vram_quadrant();
-                        return self.vram_banks[vram_page][address as usize - 0x4000 + quadrant*(128*128)];
+                        self.vram_banks[vram_page][address as usize - 0x4000 + quadrant*(128*128)]
                     }
                     GraphicsMemoryMap::BlitterRegisters => {
-                        return self.blitter.read_byte(address);
+                        self.blitter.read_byte(address);
+                        self.open_bus_value(self.open_bus.blitter)
                     }
                 }
             }
             // Cartridge
+            0x8000..=0xFFFF if self.cartridge.is_empty() => {
+                self.open_bus_value(self.open_bus.cartridge)
+            }
             0x8000..=0xFFFF => {
-                return self.cartridge.read_byte(address - 0x8000);
+                self.cartridge.read(address - 0x8000)
             }
             _ => {
                 warn!("Attempted to inaccessible memory at: ${:02X}", address);
+                self.open_bus_value(self.open_bus.unmapped)
+            }
+        };
+        self.data_bus = data;
+        data
+    }
+
+    /// The memory `address` reads from, and the offset into it, for memories that are tracked
+    /// for the uninitialized memory detector.
+    fn tracked_memory_at(&self, address: u16) -> Option<(UninitMemory, usize)> {
+        match address {
+            0x0000..=0x1FFF => {
+                let bank = self.system_control.get_ram_bank();
+                Some((UninitMemory::Ram { bank: bank as u8 }, address as usize))
             }
+            0x3000..=0x3FFF => { Some((UninitMemory::Aram, address as usize - 0x3000)) }
+            0x4000..=0x7FFF => match self.system_control.get_graphics_memory_map() {
+                GraphicsMemoryMap::VRAM => {
+                    let vram_page = self.system_control.banking_register.vram_page();
+                    let quadrant = self.blitter.vram_quadrant();
+                    Some((UninitMemory::Vram { page: vram_page }, address as usize - 0x4000 + quadrant*(128*128)))
+                }
+                _ => None,
+            },
+            _ => None,
         }
+    }
+
+    /// Counts every byte of RAM, VRAM and audio RAM as written, for when their contents
+    /// didn't come from power on and the writes that made them aren't known.
+    pub(crate) fn mark_memory_written(&mut self) {
+        self.ram_written.set_all();
+        self.vram_written.set_all();
+        self.aram.mark_written();
+    }
+
+    #[inline(always)]
+    fn check_uninit(&mut self, address: u16) {
+        if self.uninit_hits.is_none() {
+            return;
+        }
+        let Some((memory, offset)) = self.tracked_memory_at(address) else { return };
+        let written = match memory {
+            UninitMemory::Ram { bank } => self.ram_written.get(bank as usize * 0x2000 + offset),
+            UninitMemory::Vram { page } => self.vram_written.get(page as usize * 256*256 + offset),
+            UninitMemory::Aram => self.aram.is_written(offset as u16),
+        };
+        if !written {
+            let hit = UninitHit { reader: UninitReader::Cpu, address, memory, offset };
+            self.uninit_hits.as_mut().unwrap().push(hit);
+        }
+    }
+
+    fn read_watched(&mut self, address: u16, rmw: bool) -> u8 {
+        let data = self.read_byte(address);
+        self.watches.check(address, Access::Read, data, rmw);
+        data
+    }
 
-        0
+    fn open_bus_value(&self, policy: OpenBusPolicy) -> u8 {
+        match policy {
+            OpenBusPolicy::LastValue => self.data_bus,
+            OpenBusPolicy::Fixed(value) => value,
+        }
     }
 
     pub fn peek_byte_decorated(&self, address: u16) -> ByteDecorator {
@@ -234,9 +353,10 @@ impl CpuBus {
             0x0000..=0x00FF => { ZeroPage(self.ram_banks[self.system_control.get_ram_bank()][address as usize]) },
             0x0100..=0x01FF => { CpuStack(self.ram_banks[self.system_control.get_ram_bank()][address as usize]) },
             0x0200..=0x1FFF => { SystemRam(self.ram_banks[self.system_control.get_ram_bank()][address as usize]) },
-            0x2000..=0x2009 => { Unreadable(self.system_control.peek_byte(address)) },
-            // 0x2800..=0x280F => { Via(self.system_control.via_regs[(address & 0xF) as usize]) },
-            0x3000..=0x3FFF => unsafe { AudioRam(ARAM[(address - 0x3000) as usize]) },
+            0x2000..=0x2007 => { Unreadable(self.open_bus_value(self.open_bus.system_control)) },
+            0x2008..=0x2009 => { Unreadable(self.system_control.peek_byte(address)) },
+            // 0x2800..=0x280F => { Via(self.system_control.via.peek((address & 0xF) as usize)) },
+            0x3000..=0x3FFF => { ByteDecorator::AudioRam(self.aram.read(address - 0x3000)) },
             0x4000..=0x7FFF => {
                 match self.system_control.get_graphics_memory_map() {
                     GraphicsMemoryMap::FrameBuffer => {
@@ -249,11 +369,32 @@ impl CpuBus {
                         Vram(self.vram_banks[vram_page][address as usize - 0x4000 + quadrant*(128*128)])
                     }
                     GraphicsMemoryMap::BlitterRegisters => {
-                        Unreadable(0)
+                        Unreadable(self.open_bus_value(self.open_bus.blitter))
                     }
                 }
             },
-            _ => Unreadable(0),
+            0x8000..=0xFFFF if self.cartridge.is_empty() => { Unreadable(self.open_bus_value(self.open_bus.cartridge)) },
+            0x2800..=0x280F | 0x8000..=0xFFFF => Unreadable(0),
+            _ => Unreadable(self.open_bus_value(self.open_bus.unmapped)),
+        }
+    }
+
+    /// Returns what a read of `address` would, without any of a read's side effects.
+    pub fn peek_byte(&self, address: u16) -> u8 {
+        match address {
+            0x2800..=0x280F => { self.system_control.via.peek((address & 0xF) as usize) }
+            0x8000..=0xFFFF if !self.cartridge.is_empty() => { self.cartridge.read_byte(address - 0x8000) }
+            _ => match self.peek_byte_decorated(address) {
+                ZeroPage(b) | CpuStack(b) | SystemRam(b) | ByteDecorator::AudioRam(b) | Vram(b) | ByteDecorator::Framebuffer(b) | ByteDecorator::Aram(b) | Unreadable(b) => b,
+            }
+        }
+    }
+
+    /// The cartridge bank mapped at `address`, for cartridges that have banks.
+    pub fn bank_at(&self, address: u16) -> Option<u8> {
+        match address {
+            0x8000..=0xFFFF => { self.cartridge.bank_at(address - 0x8000) }
+            _ => None,
         }
     }
 
@@ -263,11 +404,187 @@ impl CpuBus {
 }
 
 impl System for CpuBus {
-    fn read(&mut self, _: &mut W65C02S, addr: u16) -> u8 {
+    // instruction and operand fetches are covered by breakpoints, not read watchpoints
+    fn read_opcode(&mut self, _: &mut W65C02S, addr: u16) -> u8 {
+        self.check_uninit(addr);
         self.read_byte(addr)
     }
 
+    fn read_operand(&mut self, _: &mut W65C02S, addr: u16) -> u8 {
+        self.check_uninit(addr);
+        self.read_byte(addr)
+    }
+
+    fn read(&mut self, _: &mut W65C02S, addr: u16) -> u8 {
+        self.check_uninit(addr);
+        self.read_watched(addr, false)
+    }
+
+    fn read_locked(&mut self, _: &mut W65C02S, addr: u16) -> u8 {
+        self.check_uninit(addr);
+        self.read_watched(addr, true)
+    }
+
+    // dummy reads don't use the value, so they aren't reads of uninitialized memory
+    fn read_opcode_spurious(&mut self, _: &mut W65C02S, addr: u16) {
+        self.read_byte(addr);
+    }
+
+    fn read_operand_spurious(&mut self, _: &mut W65C02S, addr: u16) {
+        self.read_watched(addr, false);
+    }
+
+    fn read_stack_spurious(&mut self, _: &mut W65C02S, addr: u16) {
+        self.read_watched(addr, false);
+    }
+
+    fn read_locked_spurious(&mut self, _: &mut W65C02S, addr: u16) {
+        self.read_watched(addr, true);
+    }
+
+    fn read_spurious(&mut self, _: &mut W65C02S, addr: u16) {
+        self.read_watched(addr, false);
+    }
+
     fn write(&mut self, _: &mut W65C02S, addr: u16, data: u8) {
+        self.watches.check(addr, Access::Write, data, false);
         self.write_byte(addr, data);
     }
+
+    fn write_locked(&mut self, _: &mut W65C02S, addr: u16, data: u8) {
+        self.watches.check(addr, Access::Write, data, true);
+        self.write_byte(addr, data);
+    }
+}
+
+// save state sections, see `Emulator::save_state`
+impl CpuBus {
+    pub(crate) fn save_ram(&self, out: &mut StateWriter) {
+        for bank in self.ram_banks.iter() {
+            out.put_bytes(bank);
+        }
+    }
+
+    pub(crate) fn load_ram(&mut self, input: &mut StateReader) -> Result<(), StateError> {
+        for bank in self.ram_banks.iter_mut() {
+            input.read_into(bank)?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn save_vram(&self, out: &mut StateWriter) {
+        for bank in self.vram_banks.iter() {
+            out.put_bytes(bank);
+        }
+        for quad in self.vram_quad_written {
+            out.put_bool(quad);
+        }
+    }
+
+    pub(crate) fn load_vram(&mut self, input: &mut StateReader) -> Result<(), StateError> {
+        for bank in self.vram_banks.iter_mut() {
+            input.read_into(bank)?;
+        }
+        for quad in &mut self.vram_quad_written {
+            *quad = input.get_bool()?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn save_framebuffers(&self, out: &mut StateWriter) {
+        for fb in &self.framebuffers {
+            out.put_bytes(fb.borrow().as_slice());
+        }
+    }
+
+    pub(crate) fn load_framebuffers(&mut self, input: &mut StateReader) -> Result<(), StateError> {
+        for fb in &self.framebuffers {
+            input.read_into(fb.borrow_mut().as_mut_slice())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // one address in each region that can't be read: write-only system control registers,
+    // blitter registers, the empty cartridge slot and unmapped space
+    const UNREADABLE: [u16; 4] = [0x2003, 0x4002, 0x9000, 0x2400];
+
+    fn bus() -> CpuBus {
+        let bus = CpuBus { cartridge: CartridgeType::Empty, ..CpuBus::default() };
+        assert!(matches!(bus.system_control.get_graphics_memory_map(), GraphicsMemoryMap::BlitterRegisters));
+        bus
+    }
+
+    #[test]
+    fn unreadable_regions_return_the_last_bus_value() {
+        let mut bus = bus();
+        for (i, address) in UNREADABLE.into_iter().enumerate() {
+            let value = 0x50 + i as u8;
+            // a write leaves its data on the bus
+            bus.write_byte(0x0010, value);
+            assert_eq!(bus.peek_byte(address), value, "${:04X}", address);
+            assert_eq!(bus.read_byte(address), value, "${:04X}", address);
+
+            // and so does a read
+            bus.write_byte(0x0011, value ^ 0xFF);
+            bus.write_byte(0x0012, 0);
+            assert_eq!(bus.read_byte(0x0011), value ^ 0xFF);
+            assert_eq!(bus.read_byte(address), value ^ 0xFF, "${:04X}", address);
+        }
+    }
+
+    #[test]
+    fn blitter_register_writes_stay_on_the_bus() {
+        let mut bus = bus();
+        bus.write_byte(0x4007, 0xC3);
+        assert_eq!(bus.read_byte(0x4007), 0xC3);
+        assert_eq!(bus.read_byte(0x4000), 0xC3);
+    }
+
+    #[test]
+    fn fixed_policies_apply_per_region() {
+        let mut bus = bus();
+        bus.open_bus = OpenBusTable {
+            system_control: OpenBusPolicy::Fixed(0x11),
+            blitter: OpenBusPolicy::Fixed(0x22),
+            cartridge: OpenBusPolicy::Fixed(0x33),
+            unmapped: OpenBusPolicy::Fixed(0x44),
+        };
+        bus.write_byte(0x0010, 0xAA);
+        for (address, value) in UNREADABLE.into_iter().zip([0x11, 0x22, 0x33, 0x44]) {
+            assert_eq!(bus.peek_byte(address), value, "${:04X}", address);
+            assert_eq!(bus.read_byte(address), value, "${:04X}", address);
+        }
+
+        // the other regions still return the last bus value
+        bus.open_bus.blitter = OpenBusPolicy::LastValue;
+        bus.write_byte(0x0010, 0xAA);
+        assert_eq!(bus.read_byte(0x4002), 0xAA);
+        assert_eq!(bus.read_byte(0x2003), 0x11);
+    }
+
+    #[test]
+    fn peeking_leaves_the_bus_alone() {
+        let mut bus = bus();
+        bus.write_byte(0x0010, 0x5A);
+        bus.write_byte(0x0020, 0xA5);
+        assert_eq!(bus.peek_byte(0x0010), 0x5A);
+        assert_eq!(bus.peek_byte(0x2400), 0xA5);
+        assert_eq!(bus.read_byte(0x2400), 0xA5);
+    }
+
+    #[test]
+    fn readable_regions_ignore_the_policy() {
+        let mut bus = bus();
+        bus.open_bus.unmapped = OpenBusPolicy::Fixed(0xEE);
+        bus.write_byte(0x0010, 0x77);
+        bus.write_byte(0x3010, 0x66);
+        assert_eq!(bus.read_byte(0x0010), 0x77);
+        assert_eq!(bus.read_byte(0x3010), 0x66);
+        assert_eq!(bus.read_byte(0x280E), 0x80);
+    }
 }
@@ -4,10 +4,14 @@ mod reg_etc;
 mod cpu_bus;
 mod reg_system_control;
 mod reg_blitter;
+mod via;
+mod power_on;
 
 pub use bus::*;
 pub use acp_bus::*;
 pub use cpu_bus::*;
+pub use via::*;
+pub use power_on::*;
 
 
 
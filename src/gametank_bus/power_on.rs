@@ -0,0 +1,67 @@
+/// What RAM, VRAM, audio RAM and the framebuffers hold when the console is switched on. Real
+/// SRAM powers up with whatever its cells settle to, so a game that reads memory before
+/// writing it can behave differently on hardware than under the default zero fill.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum PowerOnFill {
+    /// Everything zeroed except framebuffer 1, which is all $FF: what the emulator has always
+    /// started with.
+    #[default]
+    Standard,
+    Zeros,
+    Ones,
+    /// Random bytes. The same seed fills memory the same way every time, on every platform.
+    Random { seed: u64 },
+    /// Four bytes of $00, then four of $FF, repeated: the kind of pattern SRAM often settles to.
+    Pattern,
+}
+
+impl PowerOnFill {
+    /// A filler for a whole power on. Random fills continue one stream from memory to memory,
+    /// so each gets different contents.
+    pub fn filler(self) -> MemoryFiller {
+        let seed = match self {
+            PowerOnFill::Random { seed } => seed,
+            _ => 0,
+        };
+        MemoryFiller { mode: self, rng: SplitMix64(seed) }
+    }
+}
+
+/// SplitMix64, spelled out here so a seed fills memory the same way on every target.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    fn fill_bytes(&mut self, memory: &mut [u8]) {
+        for chunk in memory.chunks_mut(8) {
+            chunk.copy_from_slice(&self.next().to_le_bytes()[..chunk.len()]);
+        }
+    }
+}
+
+pub struct MemoryFiller {
+    mode: PowerOnFill,
+    rng: SplitMix64,
+}
+
+impl MemoryFiller {
+    pub fn fill(&mut self, memory: &mut [u8]) {
+        match self.mode {
+            PowerOnFill::Standard | PowerOnFill::Zeros => { memory.fill(0x00) }
+            PowerOnFill::Ones => { memory.fill(0xFF) }
+            PowerOnFill::Random { .. } => { self.rng.fill_bytes(memory) }
+            PowerOnFill::Pattern => {
+                for (i, byte) in memory.iter_mut().enumerate() {
+                    *byte = if i & 4 == 0 { 0x00 } else { 0xFF };
+                }
+            }
+        }
+    }
+}
@@ -1,4 +1,5 @@
 use log::warn;
+use crate::save_state::{Snapshot, StateError, StateReader, StateWriter};
 
 
 #[derive(Debug, Copy, Clone)]
@@ -17,7 +18,7 @@ impl BlitStart {
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct BlitterRegisters {
     pub vx: u8,
     pub vy: u8,
@@ -69,4 +70,24 @@ impl BlitterRegisters {
             _ => {}
         }
     }
-}
\ No newline at end of file
+}
+impl Snapshot for BlitterRegisters {
+    fn save(&self, out: &mut StateWriter) {
+        out.put_bytes(&[self.vx, self.vy, self.gx, self.gy, self.width, self.height, self.color]);
+        out.put_u8(self.start.write);
+        out.put_bool(self.start.addressed);
+    }
+
+    fn load(&mut self, input: &mut StateReader) -> Result<(), StateError> {
+        let [vx, vy, gx, gy, width, height, color] = input.get_array()?;
+        *self = BlitterRegisters {
+            vx, vy, gx, gy, width, height,
+            start: BlitStart {
+                write: input.get_u8()?,
+                addressed: input.get_bool()?,
+            },
+            color,
+        };
+        Ok(())
+    }
+}
@@ -2,6 +2,7 @@ use alloc::boxed::Box;
 use core::cell::RefCell;
 use bitfield::bitfield;
 bitfield!{
+    #[derive(Clone, Copy)]
     pub struct BankingRegister(u8);
     impl Debug;
     pub vram_page, set_vram_page: 2, 0;
@@ -12,6 +13,7 @@ bitfield!{
 }
 
 bitfield!{
+    #[derive(Clone, Copy)]
     pub struct BlitterFlags(u8);
     impl Debug;
     pub dma_enable, set_dma_enable : 0;
@@ -26,6 +28,7 @@ bitfield!{
 
 
 #[derive(Debug)]
+#[allow(clippy::upper_case_acronyms)]
 pub enum GraphicsMemoryMap {
     FrameBuffer,
     VRAM,
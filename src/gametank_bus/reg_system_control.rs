@@ -1,30 +1,10 @@
 use log::{debug, warn};
 use crate::inputs::GamePad;
 use crate::gametank_bus::reg_etc::{BankingRegister, BlitterFlags, GraphicsMemoryMap};
+use crate::gametank_bus::via::Via;
+use crate::save_state::{Snapshot, StateError, StateReader, StateWriter};
 
-pub const VIA_IORB: usize    = 0x0;
-pub const VIA_IORA: usize    = 0x1;
-pub const VIA_DDRB: usize   = 0x2;
-pub const VIA_DDRA: usize   = 0x3;
-pub const VIA_T1CL: usize   = 0x4;
-pub const VIA_T1CH: usize   = 0x5;
-pub const VIA_T1LL: usize   = 0x6;
-pub const VIA_T1LH: usize   = 0x7;
-pub const VIA_T2CL: usize   = 0x8;
-pub const VIA_T2CH: usize   = 0x9;
-pub const VIA_SR: usize     = 0xA;
-pub const VIA_ACR: usize    = 0xB;
-pub const VIA_PCR: usize    = 0xC;
-pub const VIA_IFR: usize    = 0xD;
-pub const VIA_IER: usize    = 0xE;
-pub const VIA_ORA_NH: usize = 0xF;
-
-pub const VIA_SPI_BIT_CLK : u8 = 0b00000001;
-pub const VIA_SPI_BIT_MOSI: u8 = 0b00000010;
-pub const VIA_SPI_BIT_CS  : u8 = 0b00000100;
-pub const VIA_SPI_BIT_MISO: u8 = 0b10000000;
-
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct SystemControl {
     pub reset_acp: u8,
     pub nmi_acp: u8,
@@ -32,7 +12,7 @@ pub struct SystemControl {
     // has effects on the rest of the system
     pub banking_register: BankingRegister,
 
-    pub via_regs: [u8; 16],
+    pub via: Via,
 
     pub audio_enable_sample_rate: u8,
     pub dma_flags: BlitterFlags,
@@ -56,7 +36,7 @@ impl SystemControl {
             return GraphicsMemoryMap::FrameBuffer
         }
 
-        return GraphicsMemoryMap::VRAM
+        GraphicsMemoryMap::VRAM
     }
 
     #[inline(always)]
@@ -159,8 +139,49 @@ impl SystemControl {
             byte &= !((gamepad.up as u8) << 3);
             byte &= !((gamepad.down as u8) << 2);
             byte &= !((gamepad.left as u8) << 1);
-            byte &= !((gamepad.right as u8) << 0);
+            byte &= !(gamepad.right as u8);
         }
         byte
     }
-}
\ No newline at end of file
+}
+impl Snapshot for SystemControl {
+    fn save(&self, out: &mut StateWriter) {
+        out.put_u8(self.reset_acp);
+        out.put_u8(self.nmi_acp);
+        out.put_u8(self.banking_register.0);
+        self.via.save(out);
+        out.put_u8(self.audio_enable_sample_rate);
+        out.put_u8(self.dma_flags.0);
+        for gamepad in &self.gamepads {
+            gamepad.save(out);
+        }
+    }
+
+    fn load(&mut self, input: &mut StateReader) -> Result<(), StateError> {
+        self.load_with_via(input, |via, input| via.load(input))
+    }
+}
+
+impl SystemControl {
+    /// Loads a `SYS` chunk from a version 1 save state, which only had the VIA's 16 raw
+    /// registers.
+    pub(crate) fn load_v1(&mut self, input: &mut StateReader) -> Result<(), StateError> {
+        self.load_with_via(input, |via, input| {
+            *via = Via::from_registers(&input.get_array()?);
+            Ok(())
+        })
+    }
+
+    fn load_with_via(&mut self, input: &mut StateReader, load_via: impl FnOnce(&mut Via, &mut StateReader) -> Result<(), StateError>) -> Result<(), StateError> {
+        self.reset_acp = input.get_u8()?;
+        self.nmi_acp = input.get_u8()?;
+        self.banking_register.0 = input.get_u8()?;
+        load_via(&mut self.via, input)?;
+        self.audio_enable_sample_rate = input.get_u8()?;
+        self.dma_flags.0 = input.get_u8()?;
+        for gamepad in &mut self.gamepads {
+            gamepad.load(input)?;
+        }
+        Ok(())
+    }
+}
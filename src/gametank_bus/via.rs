@@ -0,0 +1,630 @@
+use crate::save_state::{Snapshot, StateError, StateReader, StateWriter};
+
+pub const VIA_IORB: usize    = 0x0;
+pub const VIA_IORA: usize    = 0x1;
+pub const VIA_DDRB: usize   = 0x2;
+pub const VIA_DDRA: usize   = 0x3;
+pub const VIA_T1CL: usize   = 0x4;
+pub const VIA_T1CH: usize   = 0x5;
+pub const VIA_T1LL: usize   = 0x6;
+pub const VIA_T1LH: usize   = 0x7;
+pub const VIA_T2CL: usize   = 0x8;
+pub const VIA_T2CH: usize   = 0x9;
+pub const VIA_SR: usize     = 0xA;
+pub const VIA_ACR: usize    = 0xB;
+pub const VIA_PCR: usize    = 0xC;
+pub const VIA_IFR: usize    = 0xD;
+pub const VIA_IER: usize    = 0xE;
+pub const VIA_ORA_NH: usize = 0xF;
+
+pub const VIA_SPI_BIT_CLK : u8 = 0b00000001;
+pub const VIA_SPI_BIT_MOSI: u8 = 0b00000010;
+pub const VIA_SPI_BIT_CS  : u8 = 0b00000100;
+pub const VIA_SPI_BIT_MISO: u8 = 0b10000000;
+
+// interrupt flag and enable bits
+pub const VIA_INT_CA2: u8 = 0b0000_0001;
+pub const VIA_INT_CA1: u8 = 0b0000_0010;
+pub const VIA_INT_SR: u8  = 0b0000_0100;
+pub const VIA_INT_CB2: u8 = 0b0000_1000;
+pub const VIA_INT_CB1: u8 = 0b0001_0000;
+pub const VIA_INT_T2: u8  = 0b0010_0000;
+pub const VIA_INT_T1: u8  = 0b0100_0000;
+
+// auxiliary control register bits
+const ACR_T1_FREE_RUN: u8 = 0b0100_0000;
+const ACR_T1_PB7: u8 = 0b1000_0000;
+const ACR_T2_PULSE_COUNT: u8 = 0b0010_0000;
+
+/// Shift register modes, from ACR bits 2-4.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum ShiftMode {
+    Disabled,
+    InT2,
+    InPhi2,
+    InCb1,
+    OutT2FreeRunning,
+    OutT2,
+    OutPhi2,
+    OutCb1,
+}
+
+impl ShiftMode {
+    fn from_acr(acr: u8) -> Self {
+        match (acr >> 2) & 0b111 {
+            0b000 => ShiftMode::Disabled,
+            0b001 => ShiftMode::InT2,
+            0b010 => ShiftMode::InPhi2,
+            0b011 => ShiftMode::InCb1,
+            0b100 => ShiftMode::OutT2FreeRunning,
+            0b101 => ShiftMode::OutT2,
+            0b110 => ShiftMode::OutPhi2,
+            _ => ShiftMode::OutCb1,
+        }
+    }
+
+    fn shifts_out(self) -> bool {
+        matches!(self, ShiftMode::OutT2FreeRunning | ShiftMode::OutT2 | ShiftMode::OutPhi2 | ShiftMode::OutCb1)
+    }
+}
+
+/// The W65C22 versatile interface adapter at $2800: two 8-bit ports, two timers, a shift
+/// register and the interrupt logic tying them together. Its IRQ output is wired to the main
+/// cpu's IRQ line, alongside the blitter's.
+///
+/// Port pins and the CA/CB control lines that nothing drives read as high, as the pull-ups
+/// make them on the board. The CA2/CB2 handshake and pulse output modes aren't emulated.
+#[derive(Clone, Debug)]
+pub struct Via {
+    pub ora: u8,
+    pub orb: u8,
+    pub ddra: u8,
+    pub ddrb: u8,
+    /// Levels driven onto the port pins from outside. Only the bits set as inputs are read.
+    pub port_a_in: u8,
+    pub port_b_in: u8,
+    pub acr: u8,
+    pub pcr: u8,
+    pub ifr: u8,
+    pub ier: u8,
+
+    t1_counter: u16,
+    t1_latch: u16,
+    // one-shot mode only interrupts once per write to T1C-H
+    t1_armed: bool,
+    // free-running mode spends the cycle after an underflow reloading the counter
+    t1_reload: bool,
+    pb7: bool,
+
+    t2_counter: u16,
+    t2_latch_low: u8,
+    t2_armed: bool,
+
+    sr: u8,
+    // bits left to shift, 0 when the shift register is idle; stays put while free-running
+    sr_bits_left: u8,
+    // cycles until the next shift clock edge, in the T2 and phi2 modes
+    sr_divider: u8,
+    sr_clock: bool,
+
+    ca1: bool,
+    ca2: bool,
+    cb1: bool,
+    cb2: bool,
+}
+
+impl Default for Via {
+    fn default() -> Self {
+        Self {
+            ora: 0,
+            orb: 0,
+            ddra: 0,
+            ddrb: 0,
+            port_a_in: 0xFF,
+            port_b_in: 0xFF,
+            acr: 0,
+            pcr: 0,
+            ifr: 0,
+            ier: 0,
+            t1_counter: 0xFFFF,
+            t1_latch: 0xFFFF,
+            t1_armed: false,
+            t1_reload: false,
+            pb7: true,
+            t2_counter: 0xFFFF,
+            t2_latch_low: 0xFF,
+            t2_armed: false,
+            sr: 0,
+            sr_bits_left: 0,
+            sr_divider: 0,
+            sr_clock: true,
+            ca1: true,
+            ca2: true,
+            cb1: true,
+            cb2: true,
+        }
+    }
+}
+
+impl Via {
+    /// Rebuilds a VIA from the 16 raw register bytes old versions of the emulator kept. The
+    /// timers come back stopped, as they never ran back then.
+    pub(crate) fn from_registers(regs: &[u8; 16]) -> Self {
+        Self {
+            orb: regs[VIA_IORB],
+            ora: regs[VIA_IORA],
+            ddrb: regs[VIA_DDRB],
+            ddra: regs[VIA_DDRA],
+            t1_counter: u16::from_le_bytes([regs[VIA_T1CL], regs[VIA_T1CH]]),
+            t1_latch: u16::from_le_bytes([regs[VIA_T1LL], regs[VIA_T1LH]]),
+            t2_counter: u16::from_le_bytes([regs[VIA_T2CL], regs[VIA_T2CH]]),
+            sr: regs[VIA_SR],
+            acr: regs[VIA_ACR],
+            pcr: regs[VIA_PCR],
+            ifr: regs[VIA_IFR] & 0x7F,
+            ier: regs[VIA_IER] & 0x7F,
+            ..Self::default()
+        }
+    }
+
+    /// Whether the VIA is pulling its IRQ line.
+    #[inline(always)]
+    pub fn irq(&self) -> bool {
+        self.ifr & self.ier & 0x7F != 0
+    }
+
+    /// The levels on the port A pins: the output register where the pins are outputs, and
+    /// whatever drives them from outside elsewhere.
+    #[inline(always)]
+    pub fn port_a(&self) -> u8 {
+        (self.ora & self.ddra) | (self.port_a_in & !self.ddra)
+    }
+
+    /// The levels on the port B pins, with PB7 driven by timer 1 if the ACR says so.
+    #[inline(always)]
+    pub fn port_b(&self) -> u8 {
+        let pins = (self.orb & self.ddrb) | (self.port_b_in & !self.ddrb);
+        if self.acr & ACR_T1_PB7 != 0 {
+            (pins & 0x7F) | ((self.pb7 as u8) << 7)
+        } else {
+            pins
+        }
+    }
+
+    fn shift_mode(&self) -> ShiftMode {
+        ShiftMode::from_acr(self.acr)
+    }
+
+    /// Reading or writing a port register acknowledges its control line interrupts, except
+    /// for CA2/CB2 in their independent input modes.
+    fn clear_port_flags(&mut self, ca: bool) {
+        let (control, line1, line2) = if ca {
+            (self.pcr & 0x0F, VIA_INT_CA1, VIA_INT_CA2)
+        } else {
+            (self.pcr >> 4, VIA_INT_CB1, VIA_INT_CB2)
+        };
+        let independent = control & 0b1010 == 0b0010;
+        self.ifr &= !line1;
+        if !independent {
+            self.ifr &= !line2;
+        }
+    }
+
+    fn start_shifting(&mut self) {
+        self.ifr &= !VIA_INT_SR;
+        if self.shift_mode() != ShiftMode::Disabled {
+            self.sr_bits_left = 8;
+            self.sr_divider = self.t2_latch_low;
+        }
+    }
+
+    /// Reads a register without any of a read's side effects, for debuggers.
+    pub fn peek(&self, register: usize) -> u8 {
+        match register & 0xF {
+            VIA_IORB => self.port_b(),
+            VIA_IORA | VIA_ORA_NH => self.port_a(),
+            VIA_DDRB => self.ddrb,
+            VIA_DDRA => self.ddra,
+            VIA_T1CL => self.t1_counter as u8,
+            VIA_T1CH => (self.t1_counter >> 8) as u8,
+            VIA_T1LL => self.t1_latch as u8,
+            VIA_T1LH => (self.t1_latch >> 8) as u8,
+            VIA_T2CL => self.t2_counter as u8,
+            VIA_T2CH => (self.t2_counter >> 8) as u8,
+            VIA_SR => self.sr,
+            VIA_ACR => self.acr,
+            VIA_PCR => self.pcr,
+            VIA_IFR => self.ifr | ((self.irq() as u8) << 7),
+            _ => self.ier | 0x80,
+        }
+    }
+
+    pub fn read(&mut self, register: usize) -> u8 {
+        let value = self.peek(register);
+        match register & 0xF {
+            VIA_IORB => self.clear_port_flags(false),
+            VIA_IORA => self.clear_port_flags(true),
+            VIA_T1CL => self.ifr &= !VIA_INT_T1,
+            VIA_T2CL => self.ifr &= !VIA_INT_T2,
+            VIA_SR => self.start_shifting(),
+            _ => {}
+        }
+        value
+    }
+
+    pub fn write(&mut self, register: usize, data: u8) {
+        match register & 0xF {
+            VIA_IORB => {
+                self.orb = data;
+                self.clear_port_flags(false);
+            }
+            VIA_IORA => {
+                self.ora = data;
+                self.clear_port_flags(true);
+            }
+            VIA_ORA_NH => { self.ora = data }
+            VIA_DDRB => { self.ddrb = data }
+            VIA_DDRA => { self.ddra = data }
+            VIA_T1CL | VIA_T1LL => {
+                self.t1_latch = (self.t1_latch & 0xFF00) | data as u16;
+            }
+            VIA_T1CH => {
+                self.t1_latch = (self.t1_latch & 0x00FF) | (data as u16) << 8;
+                self.t1_counter = self.t1_latch;
+                self.t1_armed = true;
+                self.t1_reload = false;
+                self.ifr &= !VIA_INT_T1;
+                self.pb7 = false;
+            }
+            VIA_T1LH => {
+                self.t1_latch = (self.t1_latch & 0x00FF) | (data as u16) << 8;
+                self.ifr &= !VIA_INT_T1;
+            }
+            VIA_T2CL => { self.t2_latch_low = data }
+            VIA_T2CH => {
+                self.t2_counter = (data as u16) << 8 | self.t2_latch_low as u16;
+                self.t2_armed = true;
+                self.ifr &= !VIA_INT_T2;
+            }
+            VIA_SR => {
+                self.sr = data;
+                self.start_shifting();
+            }
+            VIA_ACR => { self.acr = data }
+            VIA_PCR => { self.pcr = data }
+            VIA_IFR => { self.ifr &= !(data & 0x7F) }
+            _ => {
+                if data & 0x80 != 0 {
+                    self.ier |= data & 0x7F;
+                } else {
+                    self.ier &= !data;
+                }
+            }
+        }
+    }
+
+    /// Advances the timers and the shift register by one cycle of the main cpu's clock.
+    pub fn tick(&mut self) {
+        if self.t1_reload {
+            self.t1_reload = false;
+            self.t1_counter = self.t1_latch;
+        } else {
+            let (counter, underflow) = self.t1_counter.overflowing_sub(1);
+            self.t1_counter = counter;
+            if underflow {
+                if self.acr & ACR_T1_FREE_RUN != 0 {
+                    self.ifr |= VIA_INT_T1;
+                    self.pb7 = !self.pb7;
+                    self.t1_reload = true;
+                } else if self.t1_armed {
+                    self.ifr |= VIA_INT_T1;
+                    self.pb7 = true;
+                    self.t1_armed = false;
+                }
+            }
+        }
+
+        if self.acr & ACR_T2_PULSE_COUNT == 0 {
+            let (counter, underflow) = self.t2_counter.overflowing_sub(1);
+            self.t2_counter = counter;
+            if underflow && self.t2_armed {
+                self.ifr |= VIA_INT_T2;
+                self.t2_armed = false;
+            }
+        }
+
+        match self.shift_mode() {
+            ShiftMode::InT2 | ShiftMode::OutT2 | ShiftMode::OutT2FreeRunning => {
+                if self.sr_bits_left > 0 {
+                    if self.sr_divider == 0 {
+                        self.sr_divider = self.t2_latch_low;
+                        self.toggle_shift_clock();
+                    } else {
+                        self.sr_divider -= 1;
+                    }
+                }
+            }
+            ShiftMode::InPhi2 | ShiftMode::OutPhi2 => {
+                if self.sr_bits_left > 0 {
+                    self.toggle_shift_clock();
+                }
+            }
+            ShiftMode::Disabled | ShiftMode::InCb1 | ShiftMode::OutCb1 => {}
+        }
+    }
+
+    fn toggle_shift_clock(&mut self) {
+        self.sr_clock = !self.sr_clock;
+        if self.sr_clock {
+            self.shift();
+        }
+    }
+
+    /// Shifts one bit in from CB2, or out onto it, on a rising shift clock edge.
+    fn shift(&mut self) {
+        let mode = self.shift_mode();
+        if mode.shifts_out() {
+            self.cb2 = self.sr & 0x80 != 0;
+            self.sr = self.sr.rotate_left(1);
+        } else {
+            self.sr = (self.sr << 1) | self.cb2 as u8;
+        }
+
+        // free-running mode recirculates the same byte until the mode changes
+        if mode == ShiftMode::OutT2FreeRunning {
+            return;
+        }
+        self.sr_bits_left = self.sr_bits_left.saturating_sub(1);
+        if self.sr_bits_left == 0 {
+            self.ifr |= VIA_INT_SR;
+        }
+    }
+
+    /// Whether a control line transition is the active edge its PCR bit selects.
+    fn is_active_edge(old: bool, new: bool, positive: bool) -> bool {
+        old != new && new == positive
+    }
+
+    pub fn set_ca1(&mut self, level: bool) {
+        if Self::is_active_edge(self.ca1, level, self.pcr & 0b0000_0001 != 0) {
+            self.ifr |= VIA_INT_CA1;
+        }
+        self.ca1 = level;
+    }
+
+    /// Drives CA2, which only has an effect when it's configured as an input.
+    pub fn set_ca2(&mut self, level: bool) {
+        let control = (self.pcr >> 1) & 0b111;
+        if control & 0b100 == 0 && Self::is_active_edge(self.ca2, level, control & 0b010 != 0) {
+            self.ifr |= VIA_INT_CA2;
+        }
+        self.ca2 = level;
+    }
+
+    /// Drives CB1, which also clocks the shift register in its external clock modes.
+    pub fn set_cb1(&mut self, level: bool) {
+        if Self::is_active_edge(self.cb1, level, self.pcr & 0b0001_0000 != 0) {
+            self.ifr |= VIA_INT_CB1;
+        }
+        let rising = !self.cb1 && level;
+        self.cb1 = level;
+        if rising && self.sr_bits_left > 0 && matches!(self.shift_mode(), ShiftMode::InCb1 | ShiftMode::OutCb1) {
+            self.shift();
+        }
+    }
+
+    /// Drives CB2, which is also the shift register's input in its shift in modes.
+    pub fn set_cb2(&mut self, level: bool) {
+        let control = (self.pcr >> 5) & 0b111;
+        if control & 0b100 == 0 && Self::is_active_edge(self.cb2, level, control & 0b010 != 0) {
+            self.ifr |= VIA_INT_CB2;
+        }
+        self.cb2 = level;
+    }
+
+    /// Drives the port B input pins. Falling edges on PB6 clock timer 2 in pulse counting mode.
+    pub fn set_port_b_input(&mut self, value: u8) {
+        let falling = self.port_b_in & !value & 0b0100_0000 != 0;
+        self.port_b_in = value;
+        if falling && self.ddrb & 0b0100_0000 == 0 && self.acr & ACR_T2_PULSE_COUNT != 0 {
+            self.t2_counter = self.t2_counter.wrapping_sub(1);
+            if self.t2_counter == 0 && self.t2_armed {
+                self.ifr |= VIA_INT_T2;
+                self.t2_armed = false;
+            }
+        }
+    }
+}
+
+impl Snapshot for Via {
+    fn save(&self, out: &mut StateWriter) {
+        out.put_bytes(&[self.ora, self.orb, self.ddra, self.ddrb, self.port_a_in, self.port_b_in, self.acr, self.pcr, self.ifr, self.ier]);
+        out.put_u16(self.t1_counter);
+        out.put_u16(self.t1_latch);
+        out.put_bool(self.t1_armed);
+        out.put_bool(self.t1_reload);
+        out.put_bool(self.pb7);
+        out.put_u16(self.t2_counter);
+        out.put_u8(self.t2_latch_low);
+        out.put_bool(self.t2_armed);
+        out.put_bytes(&[self.sr, self.sr_bits_left, self.sr_divider]);
+        out.put_bool(self.sr_clock);
+        for line in [self.ca1, self.ca2, self.cb1, self.cb2] {
+            out.put_bool(line);
+        }
+    }
+
+    fn load(&mut self, input: &mut StateReader) -> Result<(), StateError> {
+        let [ora, orb, ddra, ddrb, port_a_in, port_b_in, acr, pcr, ifr, ier] = input.get_array()?;
+        let t1_counter = input.get_u16()?;
+        let t1_latch = input.get_u16()?;
+        let t1_armed = input.get_bool()?;
+        let t1_reload = input.get_bool()?;
+        let pb7 = input.get_bool()?;
+        let t2_counter = input.get_u16()?;
+        let t2_latch_low = input.get_u8()?;
+        let t2_armed = input.get_bool()?;
+        let [sr, sr_bits_left, sr_divider] = input.get_array()?;
+        if sr_bits_left > 8 {
+            return Err(StateError::Invalid("via shift count"));
+        }
+        *self = Self {
+            ora, orb, ddra, ddrb, port_a_in, port_b_in, acr, pcr,
+            ifr: ifr & 0x7F,
+            ier: ier & 0x7F,
+            t1_counter, t1_latch, t1_armed, t1_reload, pb7,
+            t2_counter, t2_latch_low, t2_armed,
+            sr, sr_bits_left, sr_divider,
+            sr_clock: input.get_bool()?,
+            ca1: input.get_bool()?,
+            ca2: input.get_bool()?,
+            cb1: input.get_bool()?,
+            cb2: input.get_bool()?,
+        };
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(via: &mut Via, cycles: u32) {
+        for _ in 0..cycles {
+            via.tick();
+        }
+    }
+
+    #[test]
+    fn t1_one_shot_interrupts_once() {
+        let mut via = Via::default();
+        via.write(VIA_ACR, ACR_T1_PB7);
+        via.write(VIA_T1CL, 5);
+        via.write(VIA_T1CH, 0);
+        assert_eq!(via.port_b() & 0x80, 0);
+
+        // counts down to 0, then underflows on the next cycle
+        run(&mut via, 5);
+        assert_eq!(via.peek(VIA_T1CL), 0);
+        assert_eq!(via.ifr & VIA_INT_T1, 0);
+        via.tick();
+        assert_eq!(via.ifr & VIA_INT_T1, VIA_INT_T1);
+        assert_eq!(via.port_b() & 0x80, 0x80);
+
+        // the counter keeps running, but doesn't interrupt again until T1C-H is rewritten
+        via.read(VIA_T1CL);
+        assert_eq!(via.ifr & VIA_INT_T1, 0);
+        run(&mut via, 0x20000);
+        assert_eq!(via.ifr & VIA_INT_T1, 0);
+        assert_eq!(via.port_b() & 0x80, 0x80);
+    }
+
+    #[test]
+    fn t1_free_run_toggles_pb7() {
+        let mut via = Via::default();
+        via.write(VIA_ACR, ACR_T1_FREE_RUN | ACR_T1_PB7);
+        via.write(VIA_T1CL, 3);
+        via.write(VIA_T1CH, 0);
+
+        // a period is the latch plus two cycles: one to underflow and one to reload
+        let mut pb7 = false;
+        for _ in 0..4 {
+            run(&mut via, 3);
+            assert_eq!(via.port_b() & 0x80 != 0, pb7);
+            via.tick();
+            pb7 = !pb7;
+            assert_eq!(via.port_b() & 0x80 != 0, pb7);
+            assert_eq!(via.ifr & VIA_INT_T1, VIA_INT_T1);
+            via.read(VIA_T1CL);
+            via.tick();
+            assert_eq!(via.peek(VIA_T1CL), 3);
+        }
+    }
+
+    #[test]
+    fn pb7_follows_orb_without_acr_bit() {
+        let mut via = Via::default();
+        via.write(VIA_DDRB, 0x80);
+        via.write(VIA_IORB, 0x80);
+        via.write(VIA_T1CL, 1);
+        via.write(VIA_T1CH, 0);
+        assert_eq!(via.port_b() & 0x80, 0x80);
+        via.write(VIA_IORB, 0x00);
+        assert_eq!(via.port_b() & 0x80, 0);
+    }
+
+    #[test]
+    fn t2_counts_pb6_pulses() {
+        let mut via = Via::default();
+        via.write(VIA_ACR, ACR_T2_PULSE_COUNT);
+        via.write(VIA_T2CL, 3);
+        via.write(VIA_T2CH, 0);
+
+        // the clock doesn't count in pulse counting mode
+        run(&mut via, 100);
+        assert_eq!(via.peek(VIA_T2CL), 3);
+
+        for expected in [2, 1] {
+            via.set_port_b_input(0xBF);
+            via.set_port_b_input(0xFF);
+            assert_eq!(via.peek(VIA_T2CL), expected);
+            assert_eq!(via.ifr & VIA_INT_T2, 0);
+        }
+        // only falling edges count
+        via.set_port_b_input(0xFF);
+        assert_eq!(via.peek(VIA_T2CL), 1);
+        via.set_port_b_input(0xBF);
+        assert_eq!(via.ifr & VIA_INT_T2, VIA_INT_T2);
+
+        // and only the first time the counter reaches 0
+        via.read(VIA_T2CL);
+        for _ in 0..0x10000 {
+            via.set_port_b_input(0xFF);
+            via.set_port_b_input(0xBF);
+        }
+        assert_eq!(via.ifr & VIA_INT_T2, 0);
+    }
+
+    #[test]
+    fn t2_ignores_pb6_set_as_output() {
+        let mut via = Via::default();
+        via.write(VIA_ACR, ACR_T2_PULSE_COUNT);
+        via.write(VIA_DDRB, 0b0100_0000);
+        via.write(VIA_T2CL, 1);
+        via.write(VIA_T2CH, 0);
+        via.set_port_b_input(0xBF);
+        assert_eq!(via.peek(VIA_T2CL), 1);
+    }
+
+    #[test]
+    fn ier_sets_and_clears_with_bit_7() {
+        let mut via = Via::default();
+        via.write(VIA_IER, 0x80 | VIA_INT_T1 | VIA_INT_T2 | VIA_INT_CA1);
+        assert_eq!(via.ier, VIA_INT_T1 | VIA_INT_T2 | VIA_INT_CA1);
+        assert_eq!(via.read(VIA_IER), 0x80 | VIA_INT_T1 | VIA_INT_T2 | VIA_INT_CA1);
+
+        // with bit 7 clear, the set bits are disabled and the rest left alone
+        via.write(VIA_IER, VIA_INT_T2 | VIA_INT_SR);
+        assert_eq!(via.ier, VIA_INT_T1 | VIA_INT_CA1);
+        via.write(VIA_IER, 0x80);
+        assert_eq!(via.ier, VIA_INT_T1 | VIA_INT_CA1);
+
+        // a flag only pulls IRQ, and IFR bit 7, while it's enabled
+        via.write(VIA_T2CL, 0);
+        via.write(VIA_T2CH, 0);
+        via.tick();
+        assert_eq!(via.ifr, VIA_INT_T2);
+        assert!(!via.irq());
+        assert_eq!(via.peek(VIA_IFR), VIA_INT_T2);
+        via.write(VIA_IER, 0x80 | VIA_INT_T2);
+        assert!(via.irq());
+        assert_eq!(via.peek(VIA_IFR), 0x80 | VIA_INT_T2);
+
+        // writing IFR clears the flags written as 1
+        via.write(VIA_IFR, 0x80 | VIA_INT_T1);
+        assert!(via.irq());
+        via.write(VIA_IFR, VIA_INT_T2);
+        assert!(!via.irq());
+        assert_eq!(via.peek(VIA_IFR), 0);
+    }
+}
@@ -1,6 +1,7 @@
 use crate::inputs::KeyState::{Held, JustPressed, JustReleased, Released};
+use crate::save_state::{Snapshot, StateError, StateReader, StateWriter};
 
-#[derive(Debug, Default)]
+#[derive(Clone, Debug, Default)]
 pub struct GamePad {
     pub up: bool,
     pub down: bool,
@@ -14,6 +15,28 @@ pub struct GamePad {
     pub port_select: bool,
 }
 
+impl Snapshot for GamePad {
+    fn save(&self, out: &mut StateWriter) {
+        let buttons = [self.up, self.down, self.left, self.right, self.b, self.a, self.c, self.start, self.port_select];
+        for b in buttons {
+            out.put_bool(b);
+        }
+    }
+
+    fn load(&mut self, input: &mut StateReader) -> Result<(), StateError> {
+        self.up = input.get_bool()?;
+        self.down = input.get_bool()?;
+        self.left = input.get_bool()?;
+        self.right = input.get_bool()?;
+        self.b = input.get_bool()?;
+        self.a = input.get_bool()?;
+        self.c = input.get_bool()?;
+        self.start = input.get_bool()?;
+        self.port_select = input.get_bool()?;
+        Ok(())
+    }
+}
+
 #[derive(Copy, Clone, Debug)]
 #[derive(Eq, Hash, PartialEq)]
 pub enum ControllerButton {
@@ -47,7 +70,46 @@ pub enum KeyState {
 }
 
 
+impl ControllerButton {
+    pub const ALL: [ControllerButton; 8] = [
+        ControllerButton::Up, ControllerButton::Down, ControllerButton::Left, ControllerButton::Right,
+        ControllerButton::B, ControllerButton::A, ControllerButton::Start, ControllerButton::C,
+    ];
+}
+
+impl InputCommand {
+    /// Compact encoding used by movie files.
+    pub fn to_byte(&self) -> u8 {
+        match self {
+            InputCommand::Controller1(b) => { *b as u8 }
+            InputCommand::Controller2(b) => { 0x10 | *b as u8 }
+            InputCommand::PlayPause => { 0x20 }
+            InputCommand::SoftReset => { 0x21 }
+            InputCommand::HardReset => { 0x22 }
+        }
+    }
+
+    pub fn from_byte(byte: u8) -> Option<Self> {
+        match byte {
+            0x00..=0x07 => { Some(InputCommand::Controller1(ControllerButton::ALL[byte as usize])) }
+            0x10..=0x17 => { Some(InputCommand::Controller2(ControllerButton::ALL[(byte & 0x7) as usize])) }
+            0x20 => { Some(InputCommand::PlayPause) }
+            0x21 => { Some(InputCommand::SoftReset) }
+            0x22 => { Some(InputCommand::HardReset) }
+            _ => { None }
+        }
+    }
+}
+
 impl KeyState {
+    pub fn to_byte(&self) -> u8 {
+        *self as u8
+    }
+
+    pub fn from_byte(byte: u8) -> Option<Self> {
+        [JustPressed, Held, JustReleased, Released].get(byte as usize).copied()
+    }
+
     pub fn is_pressed(&self) -> bool {
         match self {
             JustPressed => { true }
@@ -2,14 +2,29 @@
 #![allow(clippy::disallowed_methods, clippy::single_match)]
 #![allow(dead_code, unused_variables, unused_imports, internal_features, static_mut_refs)]
 extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
 
 use core::fmt::Debug;
 use crate::gametank_bus::Bus;
 
 pub mod color_map;
+pub mod palette_file;
+pub mod ntsc;
+pub mod video;
 pub mod blitter;
 pub mod gametank_bus;
 pub mod cartridges;
+pub mod rom_info;
 pub mod emulator;
 pub mod inputs;
 mod audio_output;
+pub mod save_state;
+pub mod rewind;
+pub mod movie;
+pub mod debugger;
+pub mod symbols;
+pub mod trace;
+pub mod uninit;
+#[cfg(test)]
+mod test_support;
@@ -0,0 +1,298 @@
+use alloc::vec::Vec;
+use crate::inputs::{InputCommand, KeyState};
+use crate::save_state::{crc32, StateError, StateReader, StateWriter};
+
+/// Identifies a gte-core input movie file.
+pub const MOVIE_MAGIC: [u8; 4] = *b"GTMV";
+pub const MOVIE_VERSION: u16 = 1;
+
+/// A single input change, applied at the start of `frame`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct MovieEvent {
+    /// Frames since the movie started, counted in vblanks.
+    pub frame: u64,
+    pub command: InputCommand,
+    pub state: KeyState,
+}
+
+/// A recording of every input change, keyed by frame number.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Movie {
+    /// Hash of the rom the movie was recorded against, see `Emulator::rom_hash`.
+    pub rom_hash: u64,
+    /// Save state the movie starts from. Without one, playback starts from a hard reset.
+    pub start_state: Option<Vec<u8>>,
+    /// Total length in frames.
+    pub length: u64,
+    pub events: Vec<MovieEvent>,
+}
+
+impl Movie {
+    pub fn new(rom_hash: u64, start_state: Option<Vec<u8>>) -> Self {
+        Self {
+            rom_hash,
+            start_state,
+            length: 0,
+            events: Vec::new(),
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = StateWriter::new();
+        out.put_bytes(&MOVIE_MAGIC);
+        out.put_u16(MOVIE_VERSION);
+        out.put_u64(self.rom_hash);
+        out.put_u64(self.length);
+        match &self.start_state {
+            Some(state) => {
+                out.put_u32(state.len() as u32);
+                out.put_bytes(state);
+            }
+            None => { out.put_u32(0) }
+        }
+        out.put_u32(self.events.len() as u32);
+        for event in &self.events {
+            out.put_u64(event.frame);
+            out.put_u8(event.command.to_byte());
+            out.put_u8(event.state.to_byte());
+        }
+
+        let mut bytes = out.into_bytes();
+        let crc = crc32(&bytes);
+        bytes.extend_from_slice(&crc.to_le_bytes());
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, StateError> {
+        if bytes.len() < MOVIE_MAGIC.len() || bytes[..MOVIE_MAGIC.len()] != MOVIE_MAGIC {
+            return Err(StateError::BadMagic);
+        }
+        if bytes.len() < MOVIE_MAGIC.len() + 4 {
+            return Err(StateError::Truncated);
+        }
+
+        let (body, checksum) = bytes.split_at(bytes.len() - 4);
+        let mut input = StateReader::new(&body[MOVIE_MAGIC.len()..]);
+        let version = input.get_u16()?;
+        if version > MOVIE_VERSION {
+            return Err(StateError::UnsupportedVersion(version));
+        }
+        let rom_hash = input.get_u64()?;
+        let length = input.get_u64()?;
+        let start_state = match input.get_u32()? as usize {
+            0 => None,
+            len => Some(input.take(len)?.to_vec()),
+        };
+        let count = input.get_u32()? as usize;
+        if count.checked_mul(10) != Some(input.remaining()) {
+            return Err(StateError::Truncated);
+        }
+        if crc32(body) != u32::from_le_bytes(checksum.try_into().unwrap()) {
+            return Err(StateError::ChecksumMismatch);
+        }
+
+        let mut events = Vec::with_capacity(count);
+        for _ in 0..count {
+            let frame = input.get_u64()?;
+            let command = InputCommand::from_byte(input.get_u8()?).ok_or(StateError::Invalid("movie input"))?;
+            let state = KeyState::from_byte(input.get_u8()?).ok_or(StateError::Invalid("movie key state"))?;
+            if events.last().is_some_and(|e: &MovieEvent| e.frame > frame) {
+                return Err(StateError::Invalid("movie event order"));
+            }
+            events.push(MovieEvent { frame, command, state });
+        }
+
+        Ok(Self { rom_hash, start_state, length, events })
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MovieMode {
+    Recording,
+    Playback { next_event: usize },
+}
+
+/// A movie being recorded or played back by an `Emulator`.
+#[derive(Clone, Debug)]
+pub struct MovieSession {
+    pub movie: Movie,
+    pub mode: MovieMode,
+    /// Frames elapsed since the movie started.
+    pub frame: u64,
+    // inputs received since the last frame boundary, while recording
+    pending: Vec<(InputCommand, KeyState)>,
+}
+
+impl MovieSession {
+    pub fn recording(movie: Movie) -> Self {
+        Self { movie, mode: MovieMode::Recording, frame: 0, pending: Vec::new() }
+    }
+
+    pub fn playback(movie: Movie) -> Self {
+        Self { movie, mode: MovieMode::Playback { next_event: 0 }, frame: 0, pending: Vec::new() }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        match self.mode {
+            MovieMode::Recording => false,
+            MovieMode::Playback { next_event } => {
+                next_event >= self.movie.events.len() && self.frame >= self.movie.length
+            }
+        }
+    }
+
+    /// Queues an input to be recorded and applied at the next frame boundary.
+    pub(crate) fn record(&mut self, command: InputCommand, state: KeyState) {
+        self.pending.push((command, state));
+    }
+
+    /// Advances to the next frame and returns the inputs that take effect on it.
+    pub(crate) fn next_frame(&mut self) -> Vec<(InputCommand, KeyState)> {
+        self.frame += 1;
+        match &mut self.mode {
+            MovieMode::Recording => {
+                self.movie.length = self.frame;
+                let frame = self.frame;
+                let pending = core::mem::take(&mut self.pending);
+                self.movie.events.extend(pending.iter().map(|&(command, state)| MovieEvent { frame, command, state }));
+                pending
+            }
+            MovieMode::Playback { next_event } => {
+                let mut inputs = Vec::new();
+                while let Some(event) = self.movie.events.get(*next_event) {
+                    if event.frame > self.frame {
+                        break;
+                    }
+                    inputs.push((event.command, event.state));
+                    *next_event += 1;
+                }
+                inputs
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+    use crate::inputs::ControllerButton;
+    use crate::inputs::InputCommand::Controller1;
+    use crate::test_support::emulator;
+
+    fn movie(start_state: Option<Vec<u8>>) -> Movie {
+        Movie {
+            rom_hash: 0x0123_4567_89AB_CDEF,
+            start_state,
+            length: 120,
+            events: vec![
+                MovieEvent { frame: 3, command: Controller1(ControllerButton::A), state: KeyState::JustPressed },
+                MovieEvent { frame: 3, command: InputCommand::Controller2(ControllerButton::Up), state: KeyState::Held },
+                MovieEvent { frame: 90, command: Controller1(ControllerButton::A), state: KeyState::JustReleased },
+            ],
+        }
+    }
+
+    #[test]
+    fn round_trips_through_bytes() {
+        for movie in [movie(None), movie(Some(vec![1, 2, 3, 4, 5])), Movie::default()] {
+            assert_eq!(Movie::from_bytes(&movie.to_bytes()), Ok(movie));
+        }
+    }
+
+    #[test]
+    fn rejects_bad_magic() {
+        let mut bytes = movie(None).to_bytes();
+        bytes[0] = b'X';
+        assert_eq!(Movie::from_bytes(&bytes), Err(StateError::BadMagic));
+        assert_eq!(Movie::from_bytes(b""), Err(StateError::BadMagic));
+    }
+
+    #[test]
+    fn rejects_bad_event_count() {
+        // magic, version, rom hash, length, start state length
+        let count_at = 4 + 2 + 8 + 8 + 4;
+        for count in [4, 2, u32::MAX] {
+            let mut bytes = movie(None).to_bytes();
+            bytes[count_at..count_at + 4].copy_from_slice(&count.to_le_bytes());
+            assert_eq!(Movie::from_bytes(&bytes), Err(StateError::Truncated));
+        }
+    }
+
+    #[test]
+    fn rejects_bad_checksum() {
+        let mut bytes = movie(None).to_bytes();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 0xFF;
+        assert_eq!(Movie::from_bytes(&bytes), Err(StateError::ChecksumMismatch));
+    }
+
+    #[test]
+    fn rejects_events_out_of_order() {
+        let mut movie = movie(None);
+        movie.events.swap(0, 2);
+        assert_eq!(Movie::from_bytes(&movie.to_bytes()), Err(StateError::Invalid("movie event order")));
+    }
+
+    fn record(from_current_state: bool) -> (Movie, Vec<u8>) {
+        let mut emulator = emulator();
+        emulator.run_frame();
+        emulator.run_frame();
+        emulator.start_recording(from_current_state);
+        for frame in 0..30 {
+            match frame {
+                2 => emulator.set_input_state(Controller1(ControllerButton::A), KeyState::JustPressed),
+                5 => emulator.set_input_state(Controller1(ControllerButton::Start), KeyState::JustPressed),
+                11 => emulator.set_input_state(Controller1(ControllerButton::A), KeyState::JustReleased),
+                _ => {}
+            }
+            emulator.run_frame();
+        }
+        let movie = emulator.stop_recording().unwrap();
+        assert_eq!(movie.length, 30);
+        assert_eq!(movie.events.len(), 3);
+        (movie, emulator.save_state())
+    }
+
+    fn replay(movie: Movie) -> Vec<u8> {
+        let mut emulator = emulator();
+        for _ in 0..7 {
+            emulator.run_frame();
+        }
+        let length = movie.length;
+        emulator.start_playback(movie).unwrap();
+        for _ in 0..length {
+            emulator.run_frame();
+        }
+        assert!(emulator.movie.is_none());
+        emulator.save_state()
+    }
+
+    #[test]
+    fn replay_from_power_on_matches_the_recording() {
+        let (movie, recorded) = record(false);
+        assert!(movie.start_state.is_none());
+        let movie = Movie::from_bytes(&movie.to_bytes()).unwrap();
+        assert_eq!(replay(movie.clone()), recorded);
+
+        // the inputs make a difference, so playing them a frame late doesn't match
+        let mut late = movie;
+        late.events[0].frame += 1;
+        assert_ne!(replay(late), recorded);
+    }
+
+    #[test]
+    fn replay_from_a_save_state_matches_the_recording() {
+        let (movie, recorded) = record(true);
+        assert!(movie.start_state.is_some());
+        let movie = Movie::from_bytes(&movie.to_bytes()).unwrap();
+        assert_eq!(replay(movie), recorded);
+    }
+
+    #[test]
+    fn playback_rejects_other_roms() {
+        let (mut movie, _) = record(false);
+        movie.rom_hash ^= 1;
+        assert!(matches!(emulator().start_playback(movie), Err(StateError::RomMismatch { .. })));
+    }
+}
@@ -0,0 +1,149 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use core::f32::consts::FRAC_1_SQRT_2;
+use crate::color_map::RgbaFrame;
+use crate::emulator::{HEIGHT, WIDTH};
+
+/// Signal samples per pixel. The signal is sampled four times per color subcarrier cycle, so
+/// a pixel spans one and a half cycles, about as wide as a GameTank pixel is on screen. Every
+/// other pixel starts on the opposite carrier phase, which is what makes dithered patterns
+/// bleed into colors of their own.
+const SAMPLES_PER_PIXEL: usize = 6;
+const LINE_SAMPLES: usize = WIDTH as usize * SAMPLES_PER_PIXEL;
+
+// the levels the GameTank's luma, saturation and hue bits come out at, fitted to `COLOR_MAP`
+const LUMA_BASE: f32 = 0.10;
+const LUMA_STEP: f32 = 0.09;
+const SATURATION_STEP: f32 = 0.073;
+// hue 0 sits at -51 degrees from the I axis, and each hue step turns 45 degrees further
+const HUE_OFFSET_COS: f32 = 0.6293;
+const HUE_OFFSET_SIN: f32 = -0.7771;
+
+/// cos(n * 45 degrees). The carrier is sampled every 90 degrees and hues are 45 degrees apart,
+/// so these are the only angles the filter ever needs.
+const COS45: [f32; 8] = [1.0, FRAC_1_SQRT_2, 0.0, -FRAC_1_SQRT_2, -1.0, -FRAC_1_SQRT_2, 0.0, FRAC_1_SQRT_2];
+
+/// Simulates the GameTank's composite video output. Each line of the framebuffer is encoded
+/// into a composite signal from the luma (bits 0-2), saturation (bits 3-4) and hue (bits 5-7)
+/// of its pixels, then decoded back to RGB the way a TV would, artifacts included.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct NtscFilter {
+    /// How many pixels wide the TV's chroma filter is. Wider bleeds color further into the
+    /// neighbouring pixels; 1 keeps it tight but leaves more rainbow fringes on sharp edges.
+    pub chroma_bleed: u8,
+    /// Flip the carrier phase every frame as NTSC does, so the fringes crawl along edges
+    /// instead of standing still.
+    pub dot_crawl: bool,
+    /// How much of the line above to blend into each line, from 0.0 to 1.0.
+    pub scanline_blend: f32,
+}
+
+impl Default for NtscFilter {
+    fn default() -> Self {
+        Self { chroma_bleed: 2, dot_crawl: true, scanline_blend: 0.0 }
+    }
+}
+
+impl NtscFilter {
+    /// Filters a framebuffer into RGBA pixels in `out`. `frame` decides the carrier phase
+    /// when `dot_crawl` is on.
+    pub fn render_rgba(&self, framebuffer: &[u8; (WIDTH * HEIGHT) as usize], frame: u64, out: &mut RgbaFrame) {
+        let width = WIDTH as usize;
+
+        let blend = self.scanline_blend.clamp(0.0, 1.0);
+        let chroma_window = self.chroma_bleed.max(1) as usize * SAMPLES_PER_PIXEL;
+        let mut signal = vec![0.0; LINE_SAMPLES];
+        let mut decoded = vec![[0.0; 3]; width];
+        let mut previous: Vec<[f32; 3]> = Vec::new();
+
+        for (y, (line, out_line)) in framebuffer.chunks_exact(width).zip(out.chunks_exact_mut(width * 4)).enumerate() {
+            // a line is 227.5 carrier cycles and a frame 262 lines, so the phase flips every
+            // line and, with dot crawl, every frame
+            let crawl = if self.dot_crawl { frame as usize & 1 } else { 0 };
+            let phase = 2 * ((y + crawl) & 1);
+
+            encode_line(line, phase, &mut signal);
+            decode_line(&signal, phase, chroma_window, &mut decoded);
+
+            for (x, pixel) in out_line.chunks_exact_mut(4).enumerate() {
+                let mut rgb = decoded[x];
+                if let Some(above) = previous.get(x) {
+                    for (c, a) in rgb.iter_mut().zip(above) {
+                        *c = *c * (1.0 - blend) + a * blend;
+                    }
+                }
+                for (out, c) in pixel.iter_mut().zip(rgb) {
+                    *out = (c * 255.0 + 0.5).clamp(0.0, 255.0) as u8;
+                }
+                pixel[3] = 0xFF;
+            }
+            previous.clone_from(&decoded);
+        }
+    }
+}
+
+/// The carrier phase of `sample`, in steps of 90 degrees.
+fn carrier(sample: usize, phase: usize) -> usize {
+    (sample + phase) & 3
+}
+
+fn encode_line(line: &[u8], phase: usize, signal: &mut [f32]) {
+    for (x, &color) in line.iter().enumerate() {
+        let luma = LUMA_BASE + LUMA_STEP * (color & 7) as f32;
+        let saturation = SATURATION_STEP * ((color >> 3) & 3) as f32;
+        let hue = (color >> 5) as usize;
+        for n in x * SAMPLES_PER_PIXEL..(x + 1) * SAMPLES_PER_PIXEL {
+            // luma + saturation * cos(carrier - hue)
+            signal[n] = luma + saturation * COS45[(2 * carrier(n, phase) + 8 - hue) & 7];
+        }
+    }
+}
+
+/// Separates luma from chroma, demodulates the chroma and converts each pixel back to RGB.
+fn decode_line(signal: &[f32], phase: usize, chroma_window: usize, decoded: &mut [[f32; 3]]) {
+    let at = |n: isize| signal[n.clamp(0, signal.len() as isize - 1) as usize];
+
+    // a [1/2 1 1 1 1/2] notch is exactly one carrier cycle wide, so it removes the chroma of
+    // flat colors entirely; whatever chroma is left on edges is what shows up as fringes
+    let mut luma = vec![0.0; signal.len()];
+    let mut u = vec![0.0; signal.len()];
+    let mut v = vec![0.0; signal.len()];
+    for n in 0..signal.len() {
+        let i = n as isize;
+        luma[n] = (at(i - 2) * 0.5 + at(i - 1) + at(i) + at(i + 1) + at(i + 2) * 0.5) / 4.0;
+        let chroma = signal[n] - luma[n];
+        let p = carrier(n, phase);
+        u[n] = 2.0 * chroma * COS45[2 * p];
+        v[n] = 2.0 * chroma * COS45[(2 * p + 6) & 7];
+    }
+    let u = box_filter(&u, chroma_window);
+    let v = box_filter(&v, chroma_window);
+
+    for (x, rgb) in decoded.iter_mut().enumerate() {
+        let samples = x * SAMPLES_PER_PIXEL..(x + 1) * SAMPLES_PER_PIXEL;
+        let average = |s: &[f32]| s[samples.clone()].iter().sum::<f32>() / SAMPLES_PER_PIXEL as f32;
+        let (y, u, v) = (average(&luma), average(&u), average(&v));
+        let i = u * HUE_OFFSET_COS - v * HUE_OFFSET_SIN;
+        let q = u * HUE_OFFSET_SIN + v * HUE_OFFSET_COS;
+        *rgb = [
+            y + 0.956 * i + 0.621 * q,
+            y - 0.272 * i - 0.647 * q,
+            y - 1.106 * i + 1.703 * q,
+        ];
+    }
+}
+
+/// Averages every sample with the `width` around it, over only the part of that window that
+/// falls within the line.
+fn box_filter(samples: &[f32], width: usize) -> Vec<f32> {
+    let mut sums = Vec::with_capacity(samples.len() + 1);
+    sums.push(0.0);
+    for s in samples {
+        sums.push(sums.last().unwrap() + s);
+    }
+    (0..samples.len()).map(|n| {
+        let start = n.saturating_sub(width / 2);
+        let end = (start + width).min(samples.len());
+        (sums[end] - sums[start]) / (end - start) as f32
+    }).collect()
+}
@@ -0,0 +1,487 @@
+use core::fmt::{Display, Formatter};
+
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+use crate::color_map::{ColorTable, Palette};
+
+/// The palette file formats `Palette::parse` reads. All of them hold exactly 256 colors, in
+/// framebuffer byte order.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PaletteFormat {
+    /// `.pal`: 768 bytes, red, green and blue for each color. Every color is opaque.
+    Pal,
+    /// One `R G B` line of decimal values per color, as in a GIMP `.gpl` palette. A trailing
+    /// color name is ignored, as are the `GIMP Palette`, `Name:` and `Columns:` header lines,
+    /// blank lines and `#` comments.
+    Text,
+    /// An array of 256 colors, each either a `"#RRGGBB"` or `"#RRGGBBAA"` string, or an array
+    /// of three or four numbers:
+    ///
+    /// ```text
+    /// ["#1a1a1a", "#313131", [71, 71, 71], [93, 93, 93, 255], ...]
+    /// ```
+    Json,
+    /// A 256×1 image strip: either raw RGBA pixels (1024 bytes) or a binary PPM (`P6`) image
+    /// of 256 pixels.
+    Strip,
+}
+
+impl PaletteFormat {
+    /// The format files with the extension `ext` (without the dot) are in.
+    pub fn from_extension(ext: &str) -> Option<Self> {
+        match ext.to_ascii_lowercase().as_str() {
+            "pal" => Some(PaletteFormat::Pal),
+            "txt" | "gpl" => Some(PaletteFormat::Text),
+            "json" => Some(PaletteFormat::Json),
+            "rgba" | "raw" | "ppm" => Some(PaletteFormat::Strip),
+            _ => None,
+        }
+    }
+
+    /// Guesses the format from the contents of a file, for when there's no extension to go
+    /// by.
+    pub fn detect(bytes: &[u8]) -> Option<Self> {
+        let binary = match bytes.len() {
+            PAL_LEN => Some(PaletteFormat::Pal),
+            RGBA_LEN => Some(PaletteFormat::Strip),
+            _ => None,
+        };
+        let guess = if bytes.starts_with(b"P6") {
+            Some(PaletteFormat::Strip)
+        } else {
+            core::str::from_utf8(bytes).ok().map(str::trim_start).and_then(|text| {
+                if text.starts_with('[') {
+                    Some(PaletteFormat::Json)
+                } else if text.starts_with("GIMP Palette") || text.starts_with('#') || text.starts_with(|c: char| c.is_ascii_digit()) {
+                    Some(PaletteFormat::Text)
+                } else {
+                    None
+                }
+            })
+        };
+        match (guess, binary) {
+            // raw colors can happen to look like the start of a text or PPM file, so the guess
+            // only wins over a binary palette of the right size if it actually parses
+            (Some(guess), Some(binary)) if guess.read(bytes).is_err() => Some(binary),
+            (Some(guess), _) => Some(guess),
+            (None, binary) => binary,
+        }
+    }
+
+    fn read(self, bytes: &[u8]) -> Result<Box<ColorTable>, PaletteError> {
+        match self {
+            PaletteFormat::Pal => parse_pal(bytes),
+            PaletteFormat::Text => parse_text(as_text(bytes)?),
+            PaletteFormat::Json => parse_json(as_text(bytes)?),
+            PaletteFormat::Strip => parse_strip(bytes),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PaletteError {
+    /// A binary palette isn't the size its format calls for.
+    WrongSize(usize),
+    /// The file holds this many colors instead of 256.
+    WrongCount(usize),
+    /// A text palette line couldn't be parsed.
+    BadLine { line: usize, reason: &'static str },
+    /// A JSON palette couldn't be parsed at byte `offset`.
+    BadJson { offset: usize, reason: &'static str },
+    /// A PPM strip's header is malformed or describes an image that isn't 256 pixels of 8 bit
+    /// color.
+    BadImage(&'static str),
+    /// The format couldn't be told from the file name or contents.
+    UnknownFormat,
+    /// Reading the file failed, with the platform's description of why.
+    Io(String),
+}
+
+impl Display for PaletteError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            PaletteError::WrongSize(len) => write!(f, "palette file is {} bytes, expected {} or {}", len, PAL_LEN, RGBA_LEN),
+            PaletteError::WrongCount(count) => write!(f, "palette has {} colors, expected 256", count),
+            PaletteError::BadLine { line, reason } => write!(f, "bad palette on line {}: {}", line, reason),
+            PaletteError::BadJson { offset, reason } => write!(f, "bad palette json at byte {}: {}", offset, reason),
+            PaletteError::BadImage(reason) => write!(f, "bad palette image: {}", reason),
+            PaletteError::UnknownFormat => write!(f, "unknown palette file format"),
+            PaletteError::Io(e) => write!(f, "palette file error: {}", e),
+        }
+    }
+}
+
+const PAL_LEN: usize = 256 * 3;
+const RGBA_LEN: usize = 256 * 4;
+
+impl Palette {
+    /// Reads a palette file in `format`, or in the format `PaletteFormat::detect` finds if
+    /// it's `None`.
+    pub fn parse(bytes: &[u8], format: Option<PaletteFormat>) -> Result<Self, PaletteError> {
+        let format = format.or_else(|| PaletteFormat::detect(bytes)).ok_or(PaletteError::UnknownFormat)?;
+        Ok(Palette::Custom(format.read(bytes)?))
+    }
+
+    /// Reads the palette file at `path`, in the format its extension names, or else the one
+    /// its contents look like.
+    #[cfg(feature = "std")]
+    pub fn open(path: impl AsRef<std::path::Path>) -> Result<Self, PaletteError> {
+        use std::string::ToString;
+        let path = path.as_ref();
+        let bytes = std::fs::read(path).map_err(|e| PaletteError::Io(e.to_string()))?;
+        let format = path.extension().and_then(|ext| ext.to_str()).and_then(PaletteFormat::from_extension);
+        Self::parse(&bytes, format)
+    }
+}
+
+fn as_text(bytes: &[u8]) -> Result<&str, PaletteError> {
+    core::str::from_utf8(bytes).map_err(|_| PaletteError::BadLine { line: 0, reason: "palette is not utf-8" })
+}
+
+fn table(colors: &[(u8, u8, u8, u8)]) -> Result<Box<ColorTable>, PaletteError> {
+    let colors: &ColorTable = colors.try_into().map_err(|_| PaletteError::WrongCount(colors.len()))?;
+    Ok(Box::new(*colors))
+}
+
+fn parse_pal(bytes: &[u8]) -> Result<Box<ColorTable>, PaletteError> {
+    if bytes.len() != PAL_LEN {
+        return Err(PaletteError::WrongSize(bytes.len()));
+    }
+    let colors: Vec<_> = bytes.chunks_exact(3).map(|c| (c[0], c[1], c[2], 0xFF)).collect();
+    table(&colors)
+}
+
+fn parse_strip(bytes: &[u8]) -> Result<Box<ColorTable>, PaletteError> {
+    // raw pixels can start with `P6` too, so a file their size is only a PPM if it parses as one
+    if bytes.starts_with(b"P6") {
+        let ppm = parse_ppm(bytes);
+        if ppm.is_ok() || bytes.len() != RGBA_LEN {
+            return ppm;
+        }
+    }
+    if bytes.len() != RGBA_LEN {
+        return Err(PaletteError::WrongSize(bytes.len()));
+    }
+    let colors: Vec<_> = bytes.chunks_exact(4).map(|c| (c[0], c[1], c[2], c[3])).collect();
+    table(&colors)
+}
+
+fn parse_ppm(bytes: &[u8]) -> Result<Box<ColorTable>, PaletteError> {
+    // P6, then width, height and maximum value separated by whitespace and comments, then a
+    // single whitespace byte before the pixels
+    let mut rest = &bytes[2..];
+    let mut fields = [0usize; 3];
+    for field in &mut fields {
+        loop {
+            match rest.first() {
+                Some(b) if b.is_ascii_whitespace() => { rest = &rest[1..] }
+                Some(b'#') => {
+                    let end = rest.iter().position(|&b| b == b'\n').ok_or(PaletteError::BadImage("truncated header"))?;
+                    rest = &rest[end..];
+                }
+                _ => break,
+            }
+        }
+        let digits = rest.iter().take_while(|b| b.is_ascii_digit()).count();
+        if digits == 0 {
+            return Err(PaletteError::BadImage("expected a number in the header"));
+        }
+        *field = core::str::from_utf8(&rest[..digits]).unwrap().parse().map_err(|_| PaletteError::BadImage("number too large"))?;
+        rest = &rest[digits..];
+    }
+    let [width, height, max] = fields;
+    if width.checked_mul(height) != Some(256) {
+        return Err(PaletteError::BadImage("image isn't 256 pixels"));
+    }
+    if max != 255 {
+        return Err(PaletteError::BadImage("image isn't 8 bits per channel"));
+    }
+    let pixels = rest.get(1..).filter(|p| p.len() >= PAL_LEN).ok_or(PaletteError::BadImage("truncated pixels"))?;
+    parse_pal(&pixels[..PAL_LEN])
+}
+
+fn parse_text(text: &str) -> Result<Box<ColorTable>, PaletteError> {
+    let mut colors = Vec::with_capacity(256);
+    for (i, line) in text.lines().enumerate() {
+        let error = |reason| PaletteError::BadLine { line: i + 1, reason };
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line == "GIMP Palette" || line.starts_with("Name:") || line.starts_with("Columns:") {
+            continue;
+        }
+        let mut channels = line.split_whitespace().map(|v| v.parse::<u8>().map_err(|_| error("expected `R G B` values from 0 to 255")));
+        let mut channel = || channels.next().unwrap_or(Err(error("expected `R G B` values from 0 to 255")));
+        colors.push((channel()?, channel()?, channel()?, 0xFF));
+    }
+    table(&colors)
+}
+
+struct Json<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl Json<'_> {
+    fn error<T>(&self, reason: &'static str) -> Result<T, PaletteError> {
+        Err(PaletteError::BadJson { offset: self.pos, reason })
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.bytes.get(self.pos).is_some_and(|b| b.is_ascii_whitespace()) {
+            self.pos += 1;
+        }
+    }
+
+    fn peek(&mut self) -> Option<u8> {
+        self.skip_whitespace();
+        self.bytes.get(self.pos).copied()
+    }
+
+    fn expect(&mut self, byte: u8, reason: &'static str) -> Result<(), PaletteError> {
+        if self.peek() != Some(byte) {
+            return self.error(reason);
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    /// Reads `[item, item, ...]`, calling `item` for each one.
+    fn array(&mut self, mut item: impl FnMut(&mut Self) -> Result<(), PaletteError>) -> Result<(), PaletteError> {
+        self.expect(b'[', "expected `[`")?;
+        if self.peek() == Some(b']') {
+            self.pos += 1;
+            return Ok(());
+        }
+        loop {
+            item(self)?;
+            match self.peek() {
+                Some(b',') => { self.pos += 1 }
+                Some(b']') => {
+                    self.pos += 1;
+                    return Ok(());
+                }
+                _ => return self.error("expected `,` or `]`"),
+            }
+        }
+    }
+
+    fn channel(&mut self) -> Result<u8, PaletteError> {
+        self.skip_whitespace();
+        let digits = self.bytes[self.pos..].iter().take_while(|b| b.is_ascii_digit()).count();
+        let value = core::str::from_utf8(&self.bytes[self.pos..self.pos + digits]).unwrap().parse::<u8>();
+        match value {
+            Ok(value) => {
+                self.pos += digits;
+                Ok(value)
+            }
+            Err(_) => self.error("expected a whole number from 0 to 255"),
+        }
+    }
+
+    fn color(&mut self) -> Result<(u8, u8, u8, u8), PaletteError> {
+        match self.peek() {
+            Some(b'"') => {
+                self.pos += 1;
+                let start = self.pos;
+                let len = self.bytes[start..].iter().position(|&b| b == b'"').map_or(self.error("unterminated string"), Ok)?;
+                let text = core::str::from_utf8(&self.bytes[start..start + len]).unwrap();
+                let hex = text.strip_prefix('#').unwrap_or(text);
+                let channel = |i: usize| u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16);
+                let color = match hex.len() {
+                    6 if hex.is_ascii() => (channel(0), channel(1), channel(2), Ok(0xFF)),
+                    8 if hex.is_ascii() => (channel(0), channel(1), channel(2), channel(3)),
+                    _ => return self.error("expected a `#RRGGBB` or `#RRGGBBAA` color"),
+                };
+                match color {
+                    (Ok(r), Ok(g), Ok(b), Ok(a)) => {
+                        self.pos += len + 1;
+                        Ok((r, g, b, a))
+                    }
+                    _ => self.error("expected a `#RRGGBB` or `#RRGGBBAA` color"),
+                }
+            }
+            Some(b'[') => {
+                let start = self.pos;
+                let mut channels = Vec::with_capacity(4);
+                self.array(|json| {
+                    channels.push(json.channel()?);
+                    Ok(())
+                })?;
+                match channels[..] {
+                    [r, g, b] => Ok((r, g, b, 0xFF)),
+                    [r, g, b, a] => Ok((r, g, b, a)),
+                    _ => {
+                        self.pos = start;
+                        self.error("expected 3 or 4 channels")
+                    }
+                }
+            }
+            _ => self.error("expected a color string or array"),
+        }
+    }
+}
+
+fn parse_json(text: &str) -> Result<Box<ColorTable>, PaletteError> {
+    let mut json = Json { bytes: text.as_bytes(), pos: 0 };
+    let mut colors = Vec::with_capacity(256);
+    json.array(|json| {
+        colors.push(json.color()?);
+        Ok(())
+    })?;
+    if json.peek().is_some() {
+        return json.error("unexpected data after the palette");
+    }
+    table(&colors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::format;
+    use alloc::string::ToString;
+    use alloc::vec;
+    use crate::color_map::COLOR_MAP;
+
+    fn colors(palette: Result<Palette, PaletteError>) -> Result<ColorTable, PaletteError> {
+        palette.map(|p| *p.colors())
+    }
+
+    fn rgb(colors: &ColorTable) -> Vec<u8> {
+        colors.iter().flat_map(|&(r, g, b, _)| [r, g, b]).collect()
+    }
+
+    /// The default colors, with a few translucent ones for the formats that have alpha.
+    fn translucent() -> ColorTable {
+        let mut colors = COLOR_MAP;
+        colors[0].3 = 0x00;
+        colors[255].3 = 0x80;
+        colors
+    }
+
+    fn ppm(header: &str, pixels: &[u8]) -> Vec<u8> {
+        let mut file = header.as_bytes().to_vec();
+        file.extend_from_slice(pixels);
+        file
+    }
+
+    fn text(colors: &ColorTable) -> String {
+        let mut text = "GIMP Palette\nName: GameTank\nColumns: 16\n# comment\n".to_string();
+        for (i, (r, g, b, _)) in colors.iter().enumerate() {
+            text += &format!("{:3} {:3} {:3}\tcolor {}\n", r, g, b, i);
+        }
+        text
+    }
+
+    #[test]
+    fn default_colors_are_opaque() {
+        assert!(COLOR_MAP.iter().all(|c| c.3 == 0xFF));
+    }
+
+    #[test]
+    fn pal() {
+        let bytes = rgb(&COLOR_MAP);
+        assert_eq!(colors(Palette::parse(&bytes, Some(PaletteFormat::Pal))), Ok(COLOR_MAP));
+        assert_eq!(colors(Palette::parse(&bytes[1..], Some(PaletteFormat::Pal))), Err(PaletteError::WrongSize(767)));
+    }
+
+    #[test]
+    fn raw_strip() {
+        let bytes: Vec<u8> = translucent().iter().flat_map(|&(r, g, b, a)| [r, g, b, a]).collect();
+        assert_eq!(colors(Palette::parse(&bytes, Some(PaletteFormat::Strip))), Ok(translucent()));
+        assert_eq!(colors(Palette::parse(&bytes[4..], Some(PaletteFormat::Strip))), Err(PaletteError::WrongSize(1020)));
+    }
+
+    #[test]
+    fn ppm_strip() {
+        let pixels = rgb(&COLOR_MAP);
+        for header in ["P6 256 1 255\n", "P6\n# made by hand\n16 16\n255\n", "P6\t1\t256\t255 "] {
+            assert_eq!(colors(Palette::parse(&ppm(header, &pixels), None)), Ok(COLOR_MAP), "{:?}", header);
+        }
+
+        let error = |header: &str, pixels: &[u8]| colors(Palette::parse(&ppm(header, pixels), Some(PaletteFormat::Strip)));
+        assert_eq!(error("P6 16 15 255\n", &pixels), Err(PaletteError::BadImage("image isn't 256 pixels")));
+        assert_eq!(error("P6 256 1 65535\n", &pixels), Err(PaletteError::BadImage("image isn't 8 bits per channel")));
+        assert_eq!(error("P6 256 1 255\n", &pixels[1..]), Err(PaletteError::BadImage("truncated pixels")));
+        assert_eq!(error("P6 256 x 255\n", &pixels), Err(PaletteError::BadImage("expected a number in the header")));
+        assert_eq!(error("P6 # no end", &[]), Err(PaletteError::BadImage("truncated header")));
+        assert_eq!(error("P6 99999999999999999999999 1 255\n", &pixels), Err(PaletteError::BadImage("number too large")));
+        assert_eq!(error("P6 4294967296 4294967296 255\n", &pixels), Err(PaletteError::BadImage("image isn't 256 pixels")));
+    }
+
+    #[test]
+    fn text_palette() {
+        assert_eq!(colors(Palette::parse(text(&COLOR_MAP).as_bytes(), None)), Ok(COLOR_MAP));
+        assert_eq!(colors(Palette::parse(b"0 0 0\n\n# just two\n255 255 255\n", None)), Err(PaletteError::WrongCount(2)));
+
+        let reason = "expected `R G B` values from 0 to 255";
+        let bad = text(&COLOR_MAP).replacen("26\tcolor 0\n", "256\tcolor 0\n", 1);
+        assert_eq!(colors(Palette::parse(bad.as_bytes(), None)), Err(PaletteError::BadLine { line: 5, reason }));
+        assert_eq!(colors(Palette::parse(b"1 2 3\n4 5\n", Some(PaletteFormat::Text))), Err(PaletteError::BadLine { line: 2, reason }));
+        assert_eq!(colors(Palette::parse(b"1 2 \xFF", Some(PaletteFormat::Text))), Err(PaletteError::BadLine { line: 0, reason: "palette is not utf-8" }));
+    }
+
+    #[test]
+    fn json_palette() {
+        let mut json = "[".to_string();
+        for (i, &(r, g, b, a)) in translucent().iter().enumerate() {
+            if i > 0 {
+                json += ",\n";
+            }
+            json += &match i % 4 {
+                0 => format!("\"#{:02X}{:02X}{:02X}{:02X}\"", r, g, b, a),
+                1 if a == 0xFF => format!("\"{:02x}{:02x}{:02x}\"", r, g, b),
+                2 if a == 0xFF => format!("[{}, {}, {}]", r, g, b),
+                _ => format!("[ {} ,{},{}, {} ]", r, g, b, a),
+            };
+        }
+        json += "]\n";
+        assert_eq!(colors(Palette::parse(json.as_bytes(), None)), Ok(translucent()));
+
+        let error = |json: &str| colors(Palette::parse(json.as_bytes(), Some(PaletteFormat::Json)));
+        let bad_json = |offset, reason| Err(PaletteError::BadJson { offset, reason });
+        assert_eq!(error("[]"), Err(PaletteError::WrongCount(0)));
+        assert_eq!(error("[\"#000000\"]"), Err(PaletteError::WrongCount(1)));
+        assert_eq!(error("{}"), bad_json(0, "expected `[`"));
+        assert_eq!(error("[\"#00000\"]"), bad_json(2, "expected a `#RRGGBB` or `#RRGGBBAA` color"));
+        assert_eq!(error("[\"#00000G\"]"), bad_json(2, "expected a `#RRGGBB` or `#RRGGBBAA` color"));
+        assert_eq!(error("[\"#000000"), bad_json(2, "unterminated string"));
+        assert_eq!(error("[[1, 2]]"), bad_json(1, "expected 3 or 4 channels"));
+        assert_eq!(error("[[1, 2, 256]]"), bad_json(8, "expected a whole number from 0 to 255"));
+        assert_eq!(error("[[1, 2, 3] [4, 5, 6]]"), bad_json(11, "expected `,` or `]`"));
+        assert_eq!(error("[true]"), bad_json(1, "expected a color string or array"));
+        assert_eq!(error("[] []"), bad_json(3, "unexpected data after the palette"));
+    }
+
+    #[test]
+    fn detects_formats() {
+        assert_eq!(PaletteFormat::detect(&rgb(&COLOR_MAP)), Some(PaletteFormat::Pal));
+        assert_eq!(PaletteFormat::detect(&[0; RGBA_LEN]), Some(PaletteFormat::Strip));
+        assert_eq!(PaletteFormat::detect(b"P6 256 1 255\n"), Some(PaletteFormat::Strip));
+        assert_eq!(PaletteFormat::detect(b"  [\"#000000\"]"), Some(PaletteFormat::Json));
+        assert_eq!(PaletteFormat::detect(b"GIMP Palette\n"), Some(PaletteFormat::Text));
+        assert_eq!(PaletteFormat::detect(b"0 0 0\n"), Some(PaletteFormat::Text));
+        assert_eq!(PaletteFormat::detect(b"hello"), None);
+        assert_eq!(colors(Palette::parse(b"hello", None)), Err(PaletteError::UnknownFormat));
+
+        assert_eq!(PaletteFormat::from_extension("PAL"), Some(PaletteFormat::Pal));
+        assert_eq!(PaletteFormat::from_extension("gpl"), Some(PaletteFormat::Text));
+        assert_eq!(PaletteFormat::from_extension("ppm"), Some(PaletteFormat::Strip));
+        assert_eq!(PaletteFormat::from_extension("png"), None);
+    }
+
+    #[test]
+    fn binary_palettes_that_look_like_text() {
+        // printable colors that start like a text palette, a json array or a PPM header
+        for start in [&b"#12"[..], b"123", b"[12", b"P6 "] {
+            let mut pal = vec![b'A'; PAL_LEN];
+            pal[..3].copy_from_slice(start);
+            assert_eq!(PaletteFormat::detect(&pal), Some(PaletteFormat::Pal), "{:?}", start);
+            assert!(Palette::parse(&pal, None).is_ok());
+
+            let mut rgba = vec![b'A'; RGBA_LEN];
+            rgba[..3].copy_from_slice(start);
+            assert_eq!(PaletteFormat::detect(&rgba), Some(PaletteFormat::Strip), "{:?}", start);
+            assert_eq!(colors(Palette::parse(&rgba, None)).unwrap()[0], (start[0], start[1], start[2], b'A'));
+        }
+    }
+}
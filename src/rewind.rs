@@ -0,0 +1,315 @@
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+use log::debug;
+
+#[derive(Clone, Debug)]
+enum RewindFrame {
+    /// A complete save state, run-length encoded.
+    Keyframe(Vec<u8>),
+    /// A save state XORed against the preceding keyframe, run-length encoded. Most of a
+    /// GameTank's memory is VRAM which rarely changes between snapshots, so these are tiny.
+    Delta(Vec<u8>),
+}
+
+impl RewindFrame {
+    fn size(&self) -> usize {
+        match self {
+            RewindFrame::Keyframe(b) | RewindFrame::Delta(b) => b.len(),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct RewindEntry {
+    frame: u64,
+    data: RewindFrame,
+}
+
+/// A bounded history of save states, recorded every `interval` vblanks.
+#[derive(Clone, Debug)]
+pub struct RewindBuffer {
+    /// Number of vblanks between snapshots.
+    pub interval: u32,
+    /// Number of snapshots between keyframes.
+    pub keyframe_interval: u32,
+    memory_budget: usize,
+    memory_used: usize,
+
+    vblanks_until_snapshot: u32,
+    snapshots_until_keyframe: u32,
+    entries: VecDeque<RewindEntry>,
+    // uncompressed copy of the newest keyframe, which new deltas are taken against
+    basis: Vec<u8>,
+}
+
+impl RewindBuffer {
+    pub fn new(interval: u32, memory_budget: usize) -> Self {
+        Self {
+            interval: interval.max(1),
+            keyframe_interval: 60,
+            memory_budget,
+            memory_used: 0,
+            vblanks_until_snapshot: 0,
+            snapshots_until_keyframe: 0,
+            entries: VecDeque::new(),
+            basis: Vec::new(),
+        }
+    }
+
+    pub fn memory_budget(&self) -> usize {
+        self.memory_budget
+    }
+
+    /// Changes the number of bytes the history may occupy, dropping the oldest snapshots if
+    /// it's now over budget.
+    pub fn set_memory_budget(&mut self, bytes: usize) {
+        self.memory_budget = bytes;
+        self.evict();
+    }
+
+    /// Bytes currently held by recorded snapshots.
+    pub fn memory_used(&self) -> usize {
+        self.memory_used + self.basis.len()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.basis.clear();
+        self.memory_used = 0;
+        self.snapshots_until_keyframe = 0;
+    }
+
+    /// Counts down one vblank. Returns true when a snapshot should be recorded.
+    pub(crate) fn tick(&mut self) -> bool {
+        if self.vblanks_until_snapshot == 0 {
+            self.vblanks_until_snapshot = self.interval - 1;
+            true
+        } else {
+            self.vblanks_until_snapshot -= 1;
+            false
+        }
+    }
+
+    /// Records the save state taken at `frame`.
+    pub fn push(&mut self, frame: u64, state: &[u8]) {
+        let data = if self.snapshots_until_keyframe == 0 || self.basis.len() != state.len() {
+            self.snapshots_until_keyframe = self.keyframe_interval.max(1);
+            self.basis.clear();
+            self.basis.extend_from_slice(state);
+            RewindFrame::Keyframe(rle_encode(state))
+        } else {
+            let delta: Vec<u8> = state.iter().zip(&self.basis).map(|(a, b)| a ^ b).collect();
+            RewindFrame::Delta(rle_encode(&delta))
+        };
+        self.snapshots_until_keyframe -= 1;
+
+        self.memory_used += data.size();
+        self.entries.push_back(RewindEntry { frame, data });
+        self.evict();
+    }
+
+    /// Removes every snapshot newer than `frames` frames before `current_frame` and returns the
+    /// newest remaining one as a save state, along with the frame it was taken on.
+    pub fn rewind(&mut self, current_frame: u64, frames: u64) -> Option<(u64, Vec<u8>)> {
+        let target = current_frame.saturating_sub(frames);
+        if self.entries.is_empty() {
+            return None;
+        }
+        // if the history doesn't go back far enough, use the oldest snapshot
+        let index = self.entries.iter().rposition(|e| e.frame <= target).unwrap_or(0);
+
+        for dropped in self.entries.drain(index + 1..) {
+            self.memory_used -= dropped.data.size();
+        }
+
+        let state = self.decode(index)?;
+        let frame = self.entries[index].frame;
+        // the newest keyframe may be gone, so start over with a fresh one
+        self.snapshots_until_keyframe = 0;
+        self.vblanks_until_snapshot = self.interval - 1;
+        debug!("rewound {} frames to frame {}", current_frame.saturating_sub(frame), frame);
+        Some((frame, state))
+    }
+
+    fn decode(&self, index: usize) -> Option<Vec<u8>> {
+        let key_index = (0..=index).rev().find(|&i| matches!(self.entries[i].data, RewindFrame::Keyframe(_)))?;
+        let RewindFrame::Keyframe(key) = &self.entries[key_index].data else { unreachable!() };
+        let mut state = rle_decode(key);
+        if let RewindFrame::Delta(delta) = &self.entries[index].data {
+            for (s, d) in state.iter_mut().zip(rle_decode(delta)) {
+                *s ^= d;
+            }
+        }
+        Some(state)
+    }
+
+    /// Drops the oldest keyframe and its deltas until the history fits in the budget. The
+    /// newest keyframe and its deltas are always kept, or there'd be nothing to rewind to.
+    fn evict(&mut self) {
+        let is_keyframe = |e: &RewindEntry| matches!(e.data, RewindFrame::Keyframe(_));
+        while self.memory_used() > self.memory_budget && self.entries.iter().skip(1).any(is_keyframe) {
+            self.drop_oldest();
+            // deltas can't outlive their keyframe
+            while matches!(self.entries.front(), Some(RewindEntry { data: RewindFrame::Delta(_), .. })) {
+                self.drop_oldest();
+            }
+        }
+    }
+
+    fn drop_oldest(&mut self) {
+        if let Some(entry) = self.entries.pop_front() {
+            self.memory_used -= entry.data.size();
+        }
+        if self.entries.is_empty() {
+            self.basis.clear();
+            self.snapshots_until_keyframe = 0;
+        }
+    }
+}
+
+fn put_varint(out: &mut Vec<u8>, mut value: usize) {
+    while value >= 0x80 {
+        out.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn get_varint(bytes: &[u8], pos: &mut usize) -> usize {
+    let mut value = 0;
+    let mut shift = 0;
+    while let Some(&b) = bytes.get(*pos) {
+        *pos += 1;
+        value |= ((b & 0x7F) as usize) << shift;
+        if b & 0x80 == 0 {
+            break;
+        }
+        shift += 7;
+    }
+    value
+}
+
+/// Encodes `bytes` as a series of (zero run length, literal length, literals) groups.
+fn rle_encode(bytes: &[u8]) -> Vec<u8> {
+    let zero_run = |from: usize| bytes[from..].iter().take_while(|&&b| b == 0).count();
+
+    let mut out = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let zeros = zero_run(i);
+        i += zeros;
+
+        // keep short zero runs inside the literal, they're cheaper than a new group
+        let literal_start = i;
+        while i < bytes.len() {
+            if bytes[i] != 0 {
+                i += 1;
+                continue;
+            }
+            let run = zero_run(i);
+            if run >= 4 || i + run == bytes.len() {
+                break;
+            }
+            i += run;
+        }
+
+        put_varint(&mut out, zeros);
+        put_varint(&mut out, i - literal_start);
+        out.extend_from_slice(&bytes[literal_start..i]);
+    }
+    out
+}
+
+fn rle_decode(bytes: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut pos = 0;
+    while pos < bytes.len() {
+        let zeros = get_varint(bytes, &mut pos);
+        out.resize(out.len() + zeros, 0);
+        let literals = get_varint(bytes, &mut pos).min(bytes.len() - pos);
+        out.extend_from_slice(&bytes[pos..pos + literals]);
+        pos += literals;
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    fn round_trip(bytes: &[u8]) -> Vec<u8> {
+        let encoded = rle_encode(bytes);
+        assert_eq!(rle_decode(&encoded), bytes);
+        encoded
+    }
+
+    #[test]
+    fn rle_round_trips_empty_input() {
+        assert!(round_trip(&[]).is_empty());
+    }
+
+    #[test]
+    fn rle_round_trips_long_runs() {
+        // runs past 127 bytes need more than one varint byte
+        let zeros = round_trip(&[0; 100_000]);
+        assert!(zeros.len() <= 5);
+
+        let literals: Vec<u8> = (0..1000).map(|i| (i % 255 + 1) as u8).collect();
+        round_trip(&literals);
+
+        let mut mixed = vec![0; 300];
+        mixed.extend_from_slice(&literals);
+        mixed.extend_from_slice(&[0; 200]);
+        round_trip(&mixed);
+    }
+
+    #[test]
+    fn rle_round_trips_short_zero_runs() {
+        round_trip(&[1, 0, 2, 0, 0, 3, 0, 0, 0, 4, 0, 0, 0, 0, 5]);
+        round_trip(&[0, 0, 7, 0]);
+        round_trip(&[7, 0, 0, 0]);
+    }
+
+    fn state(n: u8) -> Vec<u8> {
+        let mut state = vec![0; 256];
+        state[n as usize] = n;
+        state[255] = 0xFF;
+        state
+    }
+
+    #[test]
+    fn eviction_keeps_the_newest_keyframe_group() {
+        let mut buffer = RewindBuffer::new(1, 0);
+        for n in 0..10 {
+            buffer.push(n as u64, &state(n));
+            assert!(!buffer.is_empty());
+        }
+        assert_eq!(buffer.len(), 10);
+        assert_eq!(buffer.rewind(9, 0), Some((9, state(9))));
+    }
+
+    #[test]
+    fn eviction_drops_whole_keyframe_groups() {
+        let mut buffer = RewindBuffer::new(1, usize::MAX);
+        buffer.keyframe_interval = 4;
+        for n in 0..10 {
+            buffer.push(n as u64, &state(n));
+        }
+        // keyframes at 0, 4 and 8
+        buffer.set_memory_budget(buffer.memory_used() - 1);
+        assert_eq!(buffer.len(), 6);
+        assert_eq!(buffer.rewind(9, 100), Some((4, state(4))));
+
+        buffer.set_memory_budget(0);
+        assert_eq!(buffer.len(), 1);
+    }
+}
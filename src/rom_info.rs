@@ -0,0 +1,199 @@
+use alloc::string::{String, ToString};
+use crate::cartridges::{CartridgeKind, RomError};
+
+/// Starts a `.gtr` file that carries its metadata in a header: the magic, the length of the
+/// metadata text as a little endian u16, the text itself, then the rom image.
+pub const ROM_HEADER_MAGIC: [u8; 4] = *b"GTR\x1A";
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum SaveType {
+    #[default]
+    None,
+    /// The game saves by reprogramming the cartridge's flash.
+    Flash,
+}
+
+/// What a rom says about itself. The same `key: value` lines can be stored in the file's
+/// header or in a sidecar file next to it:
+///
+/// ```text
+/// title: Tank Patrol
+/// author: A. Homebrewer
+/// cartridge: 2m
+/// save: flash
+/// controllers: 1
+/// palette: default
+/// ```
+///
+/// Every key is optional, unknown keys are ignored, and lines starting with `#` are comments.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RomInfo {
+    pub title: Option<String>,
+    pub author: Option<String>,
+    /// Decides the mapper instead of the image size, when given.
+    pub cartridge: Option<CartridgeKind>,
+    pub save: Option<SaveType>,
+    /// How many controllers the game needs plugged in.
+    pub controllers: Option<u8>,
+    /// Name of the palette the game was designed for.
+    pub palette: Option<String>,
+}
+
+impl RomInfo {
+    pub fn parse(text: &str) -> Result<Self, RomError> {
+        let mut info = RomInfo::default();
+        for (i, line) in text.lines().enumerate() {
+            let error = |reason| RomError::BadMetadata { line: i + 1, reason };
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, value) = line.split_once(':').ok_or(error("expected `key: value`"))?;
+            let value = value.trim();
+            match key.trim() {
+                "title" => { info.title = Some(value.to_string()) }
+                "author" => { info.author = Some(value.to_string()) }
+                "cartridge" => {
+                    info.cartridge = Some(match value.to_ascii_lowercase().as_str() {
+                        "8k" => CartridgeKind::Cart8k,
+                        "16k" => CartridgeKind::Cart16k,
+                        "32k" => CartridgeKind::Cart32k,
+                        "2m" => CartridgeKind::Cart2m,
+                        _ => return Err(error("unknown cartridge type")),
+                    });
+                }
+                "save" => {
+                    info.save = Some(match value.to_ascii_lowercase().as_str() {
+                        "none" => SaveType::None,
+                        "flash" => SaveType::Flash,
+                        _ => return Err(error("unknown save type")),
+                    });
+                }
+                "controllers" => {
+                    info.controllers = Some(value.parse().ok().filter(|&n| n <= 2).ok_or(error("controllers must be 0, 1 or 2"))?);
+                }
+                "palette" => { info.palette = Some(value.to_string()) }
+                _ => {}
+            }
+        }
+        Ok(info)
+    }
+
+    /// Splits a `.gtr` file into the metadata in its header, if it has one, and the rom image.
+    pub fn split_header(bytes: &[u8]) -> Result<(Option<RomInfo>, &[u8]), RomError> {
+        let Some(rest) = bytes.strip_prefix(&ROM_HEADER_MAGIC[..]) else {
+            return Ok((None, bytes));
+        };
+        let error = |reason| RomError::BadMetadata { line: 0, reason };
+        let len = rest.get(..2).ok_or(error("truncated header"))?;
+        let len = u16::from_le_bytes([len[0], len[1]]) as usize;
+        let text = rest.get(2..2 + len).ok_or(error("truncated header"))?;
+        let text = core::str::from_utf8(text).map_err(|_| error("metadata is not utf-8"))?;
+        Ok((Some(RomInfo::parse(text)?), &rest[2 + len..]))
+    }
+
+    /// Fills the fields that describe the cartridge from the one actually loaded, where the
+    /// metadata left them out.
+    pub(crate) fn complete(mut self, kind: Option<CartridgeKind>) -> Self {
+        if let Some(kind) = kind {
+            self.cartridge.get_or_insert(kind);
+            self.save.get_or_insert(if kind.has_save() { SaveType::Flash } else { SaveType::None });
+        }
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+    use crate::test_support::{counter_rom, emulator};
+
+    const METADATA: &str = "\
+# made with love
+title: Tank Patrol: Redux
+author:  A. Homebrewer
+cartridge: 2M
+
+save: flash
+controllers: 2
+palette: default
+homepage: example.com
+";
+
+    fn with_header(text: &str, rom: &[u8]) -> Vec<u8> {
+        let mut file = ROM_HEADER_MAGIC.to_vec();
+        file.extend_from_slice(&(text.len() as u16).to_le_bytes());
+        file.extend_from_slice(text.as_bytes());
+        file.extend_from_slice(rom);
+        file
+    }
+
+    #[test]
+    fn parses_every_key() {
+        assert_eq!(RomInfo::parse(METADATA), Ok(RomInfo {
+            title: Some("Tank Patrol: Redux".into()),
+            author: Some("A. Homebrewer".into()),
+            cartridge: Some(CartridgeKind::Cart2m),
+            save: Some(SaveType::Flash),
+            controllers: Some(2),
+            palette: Some("default".into()),
+        }));
+        assert_eq!(RomInfo::parse(""), Ok(RomInfo::default()));
+    }
+
+    #[test]
+    fn rejects_bad_metadata() {
+        let error = |line, reason| Err(RomError::BadMetadata { line, reason });
+        assert_eq!(RomInfo::parse("title: a\njust a title"), error(2, "expected `key: value`"));
+        assert_eq!(RomInfo::parse("cartridge: 64k"), error(1, "unknown cartridge type"));
+        assert_eq!(RomInfo::parse("save: battery"), error(1, "unknown save type"));
+        assert_eq!(RomInfo::parse("controllers: 3"), error(1, "controllers must be 0, 1 or 2"));
+        assert_eq!(RomInfo::parse("controllers: -1"), error(1, "controllers must be 0, 1 or 2"));
+    }
+
+    #[test]
+    fn splits_a_header() {
+        let rom = counter_rom();
+        let file = with_header(METADATA, &rom);
+        let (info, image) = RomInfo::split_header(&file).unwrap();
+        assert_eq!(info, Some(RomInfo::parse(METADATA).unwrap()));
+        assert_eq!(image, &rom[..]);
+    }
+
+    #[test]
+    fn files_without_a_header_are_all_rom() {
+        let rom = counter_rom();
+        assert_eq!(RomInfo::split_header(&rom), Ok((None, &rom[..])));
+        assert_eq!(RomInfo::split_header(b"GTR"), Ok((None, &b"GTR"[..])));
+    }
+
+    #[test]
+    fn rejects_bad_headers() {
+        let error = |reason| Err(RomError::BadMetadata { line: 0, reason });
+        assert_eq!(RomInfo::split_header(b"GTR\x1A\x05"), error("truncated header"));
+        assert_eq!(RomInfo::split_header(b"GTR\x1A\x05\x00abc"), error("truncated header"));
+        assert_eq!(RomInfo::split_header(b"GTR\x1A\x02\x00\xFF\xFE"), error("metadata is not utf-8"));
+        let file = with_header("title: a\nsave: sometimes", &counter_rom());
+        assert_eq!(RomInfo::split_header(&file), Err(RomError::BadMetadata { line: 2, reason: "unknown save type" }));
+    }
+
+    #[test]
+    fn loaded_roms_fill_in_the_cartridge() {
+        let mut emulator = emulator();
+        assert_eq!(emulator.rom_info().cartridge, Some(CartridgeKind::Cart8k));
+        assert_eq!(emulator.rom_info().save, Some(SaveType::None));
+
+        // the header's cartridge type wins over the image size
+        emulator.load_rom(&with_header("title: Counter\ncartridge: 32k", &counter_rom())).unwrap();
+        assert_eq!(emulator.rom_info().title.as_deref(), Some("Counter"));
+        assert_eq!(emulator.rom_info().cartridge, Some(CartridgeKind::Cart32k));
+        assert_eq!(emulator.cpu_bus.cartridge.kind(), Some(CartridgeKind::Cart32k));
+
+        // and a sidecar takes the place of the header
+        let sidecar = RomInfo::parse("cartridge: 16k").unwrap();
+        emulator.load_rom_with_info(&with_header("title: Counter\ncartridge: 32k", &counter_rom()), sidecar).unwrap();
+        assert_eq!(emulator.rom_info().title, None);
+        assert_eq!(emulator.cpu_bus.cartridge.kind(), Some(CartridgeKind::Cart16k));
+    }
+}
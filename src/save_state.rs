@@ -0,0 +1,448 @@
+use alloc::vec::Vec;
+use core::fmt::{Display, Formatter};
+use gte_w65c02s::W65C02S;
+
+/// Identifies a gte-core save state file.
+pub const MAGIC: [u8; 4] = *b"GTSS";
+/// Bumped whenever a chunk's layout changes. Files from older versions are still accepted as
+/// long as every chunk they contain can be read by the current code.
+pub const FORMAT_VERSION: u16 = 5;
+
+pub type ChunkTag = [u8; 4];
+
+pub const CHUNK_CPU: ChunkTag = *b"CPU ";
+pub const CHUNK_ACP: ChunkTag = *b"ACP ";
+pub const CHUNK_SYSTEM: ChunkTag = *b"SYS ";
+pub const CHUNK_BLITTER: ChunkTag = *b"BLIT";
+pub const CHUNK_RAM: ChunkTag = *b"RAM ";
+pub const CHUNK_VRAM: ChunkTag = *b"VRAM";
+pub const CHUNK_FRAMEBUFFERS: ChunkTag = *b"FB  ";
+pub const CHUNK_ARAM: ChunkTag = *b"ARAM";
+pub const CHUNK_CARTRIDGE: ChunkTag = *b"CART";
+/// The picture latched by the video beam, since version 5.
+pub const CHUNK_VIDEO: ChunkTag = *b"VID ";
+
+// magic, version, flags, rom hash
+const HEADER_LEN: usize = 4 + 2 + 2 + 8;
+const CHECKSUM_LEN: usize = 4;
+
+/// Errors that can occur while restoring a snapshot.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum StateError {
+    /// The data does not start with the save state magic bytes.
+    BadMagic,
+    /// The file was written by a newer, incompatible version of the format.
+    UnsupportedVersion(u16),
+    /// The state was made with a different ROM image than the one loaded.
+    RomMismatch { expected: u64, found: u64 },
+    /// The trailing checksum does not match the contents.
+    ChecksumMismatch,
+    /// A chunk required to restore the machine is missing.
+    MissingChunk(ChunkTag),
+    /// The snapshot ended before every field could be read.
+    Truncated,
+    /// A field held a value that can't describe a real machine.
+    Invalid(&'static str),
+    /// The snapshot describes a different cartridge type than the one loaded.
+    CartridgeMismatch,
+}
+
+impl Display for StateError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            StateError::BadMagic => write!(f, "not a save state"),
+            StateError::UnsupportedVersion(v) => write!(f, "unsupported save state version {}", v),
+            StateError::RomMismatch { expected, found } => write!(f, "save state is for rom {:016x}, but {:016x} is loaded", found, expected),
+            StateError::ChecksumMismatch => write!(f, "save state checksum mismatch"),
+            StateError::MissingChunk(tag) => write!(f, "save state is missing the {:?} chunk", core::str::from_utf8(tag).unwrap_or("????")),
+            StateError::Truncated => write!(f, "save state is truncated"),
+            StateError::Invalid(what) => write!(f, "save state has an invalid {}", what),
+            StateError::CartridgeMismatch => write!(f, "save state was made with a different cartridge type"),
+        }
+    }
+}
+
+/// Little-endian byte sink used by `Snapshot::save`.
+#[derive(Default, Debug)]
+pub struct StateWriter {
+    bytes: Vec<u8>,
+}
+
+impl StateWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+
+    pub fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    #[inline(always)]
+    pub fn put_u8(&mut self, value: u8) {
+        self.bytes.push(value);
+    }
+
+    #[inline(always)]
+    pub fn put_bool(&mut self, value: bool) {
+        self.bytes.push(value as u8);
+    }
+
+    #[inline(always)]
+    pub fn put_u16(&mut self, value: u16) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    #[inline(always)]
+    pub fn put_i32(&mut self, value: i32) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    #[inline(always)]
+    pub fn put_u32(&mut self, value: u32) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    #[inline(always)]
+    pub fn put_u64(&mut self, value: u64) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    #[inline(always)]
+    pub fn put_bytes(&mut self, bytes: &[u8]) {
+        self.bytes.extend_from_slice(bytes);
+    }
+
+    /// Writes a tagged, length-prefixed chunk whose payload is produced by `body`.
+    pub fn put_chunk(&mut self, tag: ChunkTag, body: impl FnOnce(&mut Self)) {
+        self.put_bytes(&tag);
+        let len_at = self.bytes.len();
+        self.put_u32(0);
+        body(self);
+        let len = (self.bytes.len() - len_at - 4) as u32;
+        self.bytes[len_at..len_at + 4].copy_from_slice(&len.to_le_bytes());
+    }
+}
+
+/// Little-endian cursor over a snapshot, used by `Snapshot::load`.
+#[derive(Debug)]
+pub struct StateReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> StateReader<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes }
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.bytes.len()
+    }
+
+    pub fn take(&mut self, len: usize) -> Result<&'a [u8], StateError> {
+        if self.bytes.len() < len {
+            return Err(StateError::Truncated);
+        }
+        let (head, tail) = self.bytes.split_at(len);
+        self.bytes = tail;
+        Ok(head)
+    }
+
+    pub fn get_array<const N: usize>(&mut self) -> Result<[u8; N], StateError> {
+        let mut out = [0; N];
+        out.copy_from_slice(self.take(N)?);
+        Ok(out)
+    }
+
+    pub fn get_u8(&mut self) -> Result<u8, StateError> {
+        Ok(self.take(1)?[0])
+    }
+
+    pub fn get_bool(&mut self) -> Result<bool, StateError> {
+        match self.get_u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(StateError::Invalid("boolean")),
+        }
+    }
+
+    pub fn get_u16(&mut self) -> Result<u16, StateError> {
+        Ok(u16::from_le_bytes(self.get_array()?))
+    }
+
+    pub fn get_i32(&mut self) -> Result<i32, StateError> {
+        Ok(i32::from_le_bytes(self.get_array()?))
+    }
+
+    pub fn get_u32(&mut self) -> Result<u32, StateError> {
+        Ok(u32::from_le_bytes(self.get_array()?))
+    }
+
+    pub fn get_u64(&mut self) -> Result<u64, StateError> {
+        Ok(u64::from_le_bytes(self.get_array()?))
+    }
+
+    pub fn read_into(&mut self, out: &mut [u8]) -> Result<(), StateError> {
+        out.copy_from_slice(self.take(out.len())?);
+        Ok(())
+    }
+}
+
+/// Reads one chunk's payload with `body`, which must consume it exactly.
+pub fn read_chunk(payload: &[u8], body: impl FnOnce(&mut StateReader) -> Result<(), StateError>) -> Result<(), StateError> {
+    let mut input = StateReader::new(payload);
+    body(&mut input)?;
+    if input.remaining() != 0 {
+        return Err(StateError::Invalid("chunk length"));
+    }
+    Ok(())
+}
+
+/// Writes the file header that precedes the chunks.
+pub fn write_header(out: &mut StateWriter, rom_hash: u64) {
+    out.put_bytes(&MAGIC);
+    out.put_u16(FORMAT_VERSION);
+    out.put_u16(0); // flags, reserved
+    out.put_u64(rom_hash);
+}
+
+/// Appends the checksum over everything written so far and returns the finished file.
+pub fn finish_file(out: StateWriter) -> Vec<u8> {
+    let mut bytes = out.into_bytes();
+    let crc = crc32(&bytes);
+    bytes.extend_from_slice(&crc.to_le_bytes());
+    bytes
+}
+
+/// A save state file whose header, checksum and chunk table have been validated.
+#[derive(Debug)]
+pub struct StateFile<'a> {
+    pub version: u16,
+    pub rom_hash: u64,
+    chunks: Vec<(ChunkTag, &'a [u8])>,
+}
+
+impl<'a> StateFile<'a> {
+    /// Validates everything that can be checked without touching the machine: magic, version,
+    /// checksum, rom hash and chunk framing.
+    pub fn parse(bytes: &'a [u8], rom_hash: u64) -> Result<Self, StateError> {
+        if bytes.len() < MAGIC.len() || bytes[..MAGIC.len()] != MAGIC {
+            return Err(StateError::BadMagic);
+        }
+        if bytes.len() < HEADER_LEN + CHECKSUM_LEN {
+            return Err(StateError::Truncated);
+        }
+
+        let (body, checksum) = bytes.split_at(bytes.len() - CHECKSUM_LEN);
+        let mut input = StateReader::new(&body[MAGIC.len()..]);
+        let version = input.get_u16()?;
+        if version > FORMAT_VERSION {
+            return Err(StateError::UnsupportedVersion(version));
+        }
+        let _flags = input.get_u16()?;
+        let found = input.get_u64()?;
+
+        let mut chunks = Vec::new();
+        while input.remaining() > 0 {
+            let tag: ChunkTag = input.get_array()?;
+            let len = input.get_u32()? as usize;
+            chunks.push((tag, input.take(len)?));
+        }
+
+        if crc32(body) != u32::from_le_bytes(checksum.try_into().unwrap()) {
+            return Err(StateError::ChecksumMismatch);
+        }
+        if found != rom_hash {
+            return Err(StateError::RomMismatch { expected: rom_hash, found });
+        }
+
+        Ok(Self { version, rom_hash: found, chunks })
+    }
+
+    /// Returns the payload of the chunk with the given tag.
+    pub fn chunk(&self, tag: ChunkTag) -> Result<&'a [u8], StateError> {
+        self.chunks.iter()
+            .find(|(t, _)| *t == tag)
+            .map(|(_, payload)| *payload)
+            .ok_or(StateError::MissingChunk(tag))
+    }
+}
+
+/// 64-bit FNV-1a, used to identify rom images.
+pub fn fnv1a64(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for &b in bytes {
+        hash ^= b as u64;
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    hash
+}
+
+const CRC32_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = (crc >> 1) ^ (0xEDB8_8320 & (crc & 1).wrapping_neg());
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+/// CRC-32 (IEEE 802.3), used to detect corrupted files.
+pub fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &b in bytes {
+        crc = (crc >> 8) ^ CRC32_TABLE[((crc ^ b as u32) & 0xFF) as usize];
+    }
+    !crc
+}
+
+/// Serialization hook for every piece of machine state.
+///
+/// `save` must write exactly what `load` reads back, in the same order.
+pub trait Snapshot {
+    fn save(&self, out: &mut StateWriter);
+    fn load(&mut self, input: &mut StateReader) -> Result<(), StateError>;
+}
+
+impl Snapshot for W65C02S {
+    fn save(&self, out: &mut StateWriter) {
+        out.put_bytes(&self.snapshot());
+    }
+
+    fn load(&mut self, input: &mut StateReader) -> Result<(), StateError> {
+        *self = W65C02S::from_snapshot(&input.get_array()?).ok_or(StateError::Invalid("cpu state"))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{counter_rom, emulator};
+
+    const CHUNKS: [ChunkTag; 10] = [CHUNK_CPU, CHUNK_ACP, CHUNK_SYSTEM, CHUNK_BLITTER, CHUNK_RAM, CHUNK_VRAM, CHUNK_FRAMEBUFFERS, CHUNK_ARAM, CHUNK_CARTRIDGE, CHUNK_VIDEO];
+
+    fn saved_after(frames: u32) -> Vec<u8> {
+        let mut emulator = emulator();
+        for _ in 0..frames {
+            emulator.run_frame();
+        }
+        emulator.save_state()
+    }
+
+    /// Rebuilds `state` with the payload of `tag` replaced, or left out if `payload` is `None`.
+    fn with_chunk(state: &[u8], tag: ChunkTag, payload: Option<&[u8]>) -> Vec<u8> {
+        let rom_hash = fnv1a64(&counter_rom());
+        let file = StateFile::parse(state, rom_hash).unwrap();
+        let mut out = StateWriter::new();
+        write_header(&mut out, rom_hash);
+        for t in CHUNKS {
+            match (t == tag, payload) {
+                (false, _) => out.put_chunk(t, |out| out.put_bytes(file.chunk(t).unwrap())),
+                (true, Some(payload)) => out.put_chunk(t, |out| out.put_bytes(payload)),
+                (true, None) => {}
+            }
+        }
+        finish_file(out)
+    }
+
+    #[test]
+    fn crc32_check_value() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn save_load_run_is_deterministic() {
+        let mut original = emulator();
+        for _ in 0..5 {
+            original.run_frame();
+        }
+        let state = original.save_state();
+
+        let mut restored = emulator();
+        restored.load_state(&state).unwrap();
+        assert_eq!(restored.save_state(), state);
+
+        for _ in 0..10 {
+            original.run_frame();
+            restored.run_frame();
+        }
+        assert_eq!(restored.save_state(), original.save_state());
+        assert_ne!(original.save_state(), state);
+    }
+
+    #[test]
+    fn rejects_truncated_files() {
+        let state = saved_after(1);
+        let mut emulator = emulator();
+        assert_eq!(emulator.load_state(&state[..HEADER_LEN]), Err(StateError::Truncated));
+        assert_eq!(emulator.load_state(&state[..state.len() / 2]), Err(StateError::Truncated));
+    }
+
+    #[test]
+    fn rejects_bad_magic() {
+        let mut state = saved_after(1);
+        state[0] = b'X';
+        assert_eq!(emulator().load_state(&state), Err(StateError::BadMagic));
+        assert_eq!(emulator().load_state(b"GT"), Err(StateError::BadMagic));
+    }
+
+    #[test]
+    fn rejects_newer_versions() {
+        let mut state = saved_after(1);
+        state[4..6].copy_from_slice(&(FORMAT_VERSION + 1).to_le_bytes());
+        assert_eq!(emulator().load_state(&state), Err(StateError::UnsupportedVersion(FORMAT_VERSION + 1)));
+    }
+
+    #[test]
+    fn rejects_checksum_mismatch() {
+        let mut state = saved_after(1);
+        let middle = state.len() / 2;
+        state[middle] ^= 0x01;
+        assert_eq!(emulator().load_state(&state), Err(StateError::ChecksumMismatch));
+    }
+
+    #[test]
+    fn rejects_other_roms() {
+        let state = saved_after(1);
+        let mut rom = counter_rom();
+        rom[0x100] = 0xFF;
+        let mut other = emulator();
+        other.load_rom(&rom).unwrap();
+        assert_eq!(other.load_state(&state), Err(StateError::RomMismatch {
+            expected: fnv1a64(&rom),
+            found: fnv1a64(&counter_rom()),
+        }));
+    }
+
+    #[test]
+    fn rejects_missing_chunks() {
+        let state = with_chunk(&saved_after(1), CHUNK_RAM, None);
+        assert_eq!(emulator().load_state(&state), Err(StateError::MissingChunk(CHUNK_RAM)));
+    }
+
+    #[test]
+    fn failed_load_leaves_the_machine_unchanged() {
+        // every chunk before the cartridge is applied before the bad cartridge type is found
+        let state = with_chunk(&saved_after(5), CHUNK_CARTRIDGE, Some(&[9]));
+        let mut emulator = emulator();
+        emulator.run_frame();
+        let before = emulator.save_state();
+        assert_eq!(emulator.load_state(&state), Err(StateError::Invalid("cartridge type")));
+        assert_eq!(emulator.save_state(), before);
+    }
+}
@@ -0,0 +1,421 @@
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt::{Display, Formatter};
+
+/// Size of one switchable bank of a 2M cartridge.
+const BANK_SIZE: u32 = 0x4000;
+
+/// Labels without a known size still name the bytes after them, up to this distance.
+const MAX_UNSIZED_OFFSET: u16 = 0x100;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct SymbolError {
+    /// 1-based line of the file the error was found on.
+    pub line: usize,
+    pub reason: &'static str,
+}
+
+impl Display for SymbolError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.reason)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Symbol {
+    pub name: String,
+    pub address: u16,
+    /// The 2M cartridge bank the symbol lives in, if it's in banked rom.
+    pub bank: Option<u8>,
+    /// Size in bytes, 0 if unknown.
+    pub size: u16,
+    /// Equates name constants and registers rather than code or data.
+    pub is_equate: bool,
+}
+
+#[derive(Clone, Debug)]
+struct LineSpan {
+    start: u16,
+    // exclusive, so a span can reach $FFFF
+    end: u32,
+    bank: Option<u8>,
+    file: usize,
+    line: u32,
+}
+
+/// A symbol name plus an offset into it, displayed like `player_update+$12`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Label<'a> {
+    pub name: &'a str,
+    pub offset: u16,
+}
+
+impl Display for Label<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        if self.offset == 0 {
+            write!(f, "{}", self.name)
+        } else {
+            write!(f, "{}+${:X}", self.name, self.offset)
+        }
+    }
+}
+
+/// Banks match unless both are known and differ. Symbols and addresses outside banked rom,
+/// or on cartridges without banking, have no bank.
+fn bank_matches(a: Option<u8>, b: Option<u8>) -> bool {
+    a.is_none() || b.is_none() || a == b
+}
+
+/// Symbols and source lines for one address space, loaded from ld65 debug info or VICE
+/// label files.
+#[derive(Clone, Debug, Default)]
+pub struct SymbolTable {
+    // sorted by address
+    symbols: Vec<Symbol>,
+    files: Vec<String>,
+    // sorted by start
+    lines: Vec<LineSpan>,
+    // how far back from an address a label covering it can be
+    reach: u16,
+}
+
+impl SymbolTable {
+    pub fn is_empty(&self) -> bool {
+        self.symbols.is_empty() && self.lines.is_empty()
+    }
+
+    pub fn symbols(&self) -> &[Symbol] {
+        &self.symbols
+    }
+
+    pub fn add_symbol(&mut self, symbol: Symbol) {
+        let index = self.symbols.partition_point(|s| s.address <= symbol.address);
+        self.symbols.insert(index, symbol);
+        self.finish();
+    }
+
+    /// Adds everything from `other`, e.g. the ACP program's labels next to the main program's.
+    pub fn merge(&mut self, other: SymbolTable) {
+        let file_base = self.files.len();
+        self.files.extend(other.files);
+        self.lines.extend(other.lines.into_iter().map(|l| LineSpan { file: l.file + file_base, ..l }));
+        self.symbols.extend(other.symbols);
+        self.finish();
+    }
+
+    fn finish(&mut self) {
+        self.symbols.sort_by_key(|s| s.address);
+        self.lines.sort_by_key(|l| l.start);
+        self.reach = self.symbols.iter().map(|s| s.size).max().unwrap_or(0).max(MAX_UNSIZED_OFFSET);
+    }
+
+    /// Finds a symbol by name, returning its address and bank.
+    pub fn address_of(&self, name: &str) -> Option<(u16, Option<u8>)> {
+        self.symbols.iter().find(|s| s.name == name).map(|s| (s.address, s.bank))
+    }
+
+    /// Names `address`, as seen with `bank` mapped in: the closest label at or before it, or an
+    /// equate at exactly that address.
+    pub fn label(&self, address: u16, bank: Option<u8>) -> Option<Label<'_>> {
+        let end = self.symbols.partition_point(|s| s.address <= address);
+        let candidates = self.symbols[..end].iter().rev().filter(|s| bank_matches(s.bank, bank));
+
+        let mut best: Option<&Symbol> = None;
+        for symbol in candidates {
+            let offset = address - symbol.address;
+            if offset >= self.reach || best.is_some_and(|b| b.address != symbol.address) {
+                break;
+            }
+            let in_range = if symbol.size > 0 { offset < symbol.size } else { offset < MAX_UNSIZED_OFFSET };
+            if !in_range || (symbol.is_equate && offset != 0) {
+                continue;
+            }
+            // at the same address, labels win over equates
+            if best.is_none_or(|b| b.is_equate && !symbol.is_equate) {
+                best = Some(symbol);
+            }
+        }
+        best.map(|s| Label { name: &s.name, offset: address - s.address })
+    }
+
+    /// The source file and line that generated the byte at `address`, if known. When several
+    /// lines cover it (a C line and the assembly it expanded to), the narrowest one wins.
+    pub fn source_line(&self, address: u16, bank: Option<u8>) -> Option<(&str, u32)> {
+        let end = self.lines.partition_point(|l| l.start <= address);
+        self.lines[..end].iter()
+            .filter(|l| (address as u32) < l.end && bank_matches(l.bank, bank))
+            .min_by_key(|l| l.end - l.start as u32)
+            .map(|l| (self.files[l.file].as_str(), l.line))
+    }
+
+    /// Parses a VICE label file, as written by `ld65 -Ln`: lines of `al <address> .<name>`.
+    /// Addresses above $FFFF carry the bank in their top byte.
+    pub fn from_vice_labels(text: &str) -> Result<Self, SymbolError> {
+        let mut table = SymbolTable::default();
+        for (i, line) in text.lines().enumerate() {
+            let error = |reason| SymbolError { line: i + 1, reason };
+            let mut words = line.split_whitespace();
+            if words.next() != Some("al") {
+                continue;
+            }
+            let address = words.next().ok_or(error("missing address"))?;
+            let address = address.strip_prefix("C:").unwrap_or(address);
+            let value = u32::from_str_radix(address, 16).map_err(|_| error("bad address"))?;
+            let name = words.next().ok_or(error("missing name"))?;
+            table.symbols.push(Symbol {
+                name: name.strip_prefix('.').unwrap_or(name).to_string(),
+                address: value as u16,
+                bank: (value > 0xFFFF).then_some((value >> 16) as u8),
+                size: 0,
+                is_equate: false,
+            });
+        }
+        table.finish();
+        Ok(table)
+    }
+
+    /// Parses the debug info `ld65 --dbgfile` writes: symbols with their sizes, and the source
+    /// lines every span of output bytes came from. Symbols in rom get the bank their file
+    /// offset falls in, for 2M cartridges.
+    pub fn from_ld65_dbg(text: &str) -> Result<Self, SymbolError> {
+        struct Segment { start: u32, file_offset: Option<u32> }
+        struct Span { segment: u32, start: u32, size: u32 }
+
+        let mut files = BTreeMap::new();
+        let mut segments = BTreeMap::new();
+        let mut spans = BTreeMap::new();
+        // (record line, file id, line number, span ids)
+        let mut lines = Vec::new();
+        let mut table = SymbolTable::default();
+        let mut symbols = Vec::new();
+
+        for (i, line) in text.lines().enumerate() {
+            let error = |reason| SymbolError { line: i + 1, reason };
+            let Some((kind, rest)) = line.split_once(|c: char| c.is_ascii_whitespace()) else { continue };
+            let fields = parse_fields(rest).ok_or(error("malformed record"))?;
+            let get = |key: &str| fields.iter().find(|(k, _)| *k == key).map(|(_, v)| *v);
+            let number = |key: &str| get(key).map(|v| parse_number(v).ok_or(error("bad number"))).transpose();
+            let id = || number("id")?.ok_or(error("missing id"));
+
+            match kind {
+                "file" => {
+                    let name = get("name").ok_or(error("missing file name"))?;
+                    files.insert(id()?, table.files.len());
+                    table.files.push(name.to_string());
+                }
+                "seg" => {
+                    let start = number("start")?.ok_or(error("missing segment start"))?;
+                    let file_offset = if get("oname").is_some() { number("ooffs")? } else { None };
+                    segments.insert(id()?, Segment { start, file_offset });
+                }
+                "span" => {
+                    let segment = number("seg")?.ok_or(error("missing span segment"))?;
+                    let start = number("start")?.ok_or(error("missing span start"))?;
+                    let size = number("size")?.unwrap_or(0);
+                    spans.insert(id()?, Span { segment, start, size });
+                }
+                "line" => {
+                    let Some(span_ids) = get("span") else { continue };
+                    let file = number("file")?.ok_or(error("missing line file"))?;
+                    let number_in_file = number("line")?.ok_or(error("missing line number"))?;
+                    let span_ids = span_ids.split('+').map(parse_number).collect::<Option<Vec<_>>>().ok_or(error("bad span list"))?;
+                    lines.push((i + 1, file, number_in_file, span_ids));
+                }
+                "sym" => {
+                    let kind = get("type").unwrap_or("lab");
+                    if kind == "imp" {
+                        continue; // the export has the same symbol
+                    }
+                    let Some(value) = number("val")? else { continue };
+                    let name = get("name").ok_or(error("missing symbol name"))?;
+                    let size = number("size")?.unwrap_or(0);
+                    symbols.push((name, value, size, number("seg")?, kind == "equ"));
+                }
+                _ => {}
+            }
+        }
+
+        let bank_of = |segment: Option<&Segment>, address: u32| -> Option<u8> {
+            let segment = segment?;
+            let file_offset = segment.file_offset?.checked_add(address.checked_sub(segment.start)?)?;
+            (address >= 0x8000).then_some((file_offset / BANK_SIZE) as u8)
+        };
+
+        for (name, value, size, segment, is_equate) in symbols {
+            table.symbols.push(Symbol {
+                name: name.to_string(),
+                address: value as u16,
+                bank: bank_of(segment.and_then(|s| segments.get(&s)), value),
+                size: size as u16,
+                is_equate,
+            });
+        }
+
+        for (line, file, number_in_file, span_ids) in lines {
+            let error = |reason| SymbolError { line, reason };
+            let file = *files.get(&file).ok_or(error("unknown file"))?;
+            for span_id in span_ids {
+                let span = spans.get(&span_id).ok_or(error("unknown span"))?;
+                let segment = segments.get(&span.segment).ok_or(error("unknown segment"))?;
+                let start = segment.start.checked_add(span.start).ok_or(error("span start out of range"))?;
+                let end = start.checked_add(span.size).ok_or(error("span end out of range"))?;
+                table.lines.push(LineSpan {
+                    start: start as u16,
+                    end: end.min(0x10000),
+                    bank: bank_of(Some(segment), start),
+                    file,
+                    line: number_in_file,
+                });
+            }
+        }
+        table.finish();
+        Ok(table)
+    }
+}
+
+/// Splits `key=value,key="quoted, value",...` into pairs, with quotes removed.
+fn parse_fields(text: &str) -> Option<Vec<(&str, &str)>> {
+    let mut fields = Vec::new();
+    let mut rest = text.trim();
+    while !rest.is_empty() {
+        let (key, after) = rest.split_once('=')?;
+        let (value, after) = if let Some(quoted) = after.strip_prefix('"') {
+            let end = quoted.find('"')?;
+            (&quoted[..end], &quoted[end + 1..])
+        } else {
+            after.split_once(',').map_or((after, ""), |(v, a)| (v, a))
+        };
+        fields.push((key.trim(), value));
+        rest = after.strip_prefix(',').unwrap_or(after).trim_start();
+    }
+    Some(fields)
+}
+
+fn parse_number(text: &str) -> Option<u32> {
+    match text.strip_prefix("0x") {
+        Some(hex) => u32::from_str_radix(hex, 16).ok(),
+        None => text.parse().ok(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // trimmed from a 2M cartridge build: a fixed bank, two switchable banks and the zero page
+    const DBG: &str = "\
+version\tmajor=2,minor=0
+info\tcsym=0,file=2,lib=0,line=4,mod=2,scope=2,seg=4,span=4,sym=6,type=3
+file\tid=0,name=\"main.s\",size=1024,mtime=0x60000000,mod=0
+file\tid=1,name=\"src/bank, data.s\",size=200,mtime=0x60000000,mod=1
+seg\tid=0,name=\"ZEROPAGE\",start=0x000000,size=0x0004,addrsize=zeropage,type=rw
+seg\tid=1,name=\"CODE\",start=0x00C000,size=0x0100,addrsize=absolute,type=ro,oname=\"game.gtr\",ooffs=2080768
+seg\tid=2,name=\"BANK0\",start=0x008000,size=0x0200,addrsize=absolute,type=ro,oname=\"game.gtr\",ooffs=0
+seg\tid=3,name=\"BANK1\",start=0x008000,size=0x0200,addrsize=absolute,type=ro,oname=\"game.gtr\",ooffs=16384
+span\tid=0,seg=1,start=0,size=3,type=1
+span\tid=1,seg=1,start=3,size=2
+span\tid=2,seg=2,start=16,size=4
+span\tid=3,seg=3,start=0,size=1
+line\tid=0,file=0,line=10,span=0
+line\tid=1,file=0,line=11,span=1
+line\tid=2,file=1,line=5,span=2+3
+line\tid=3,file=0,line=1,type=2
+sym\tid=0,name=\"reset\",addrsize=absolute,size=5,scope=0,def=0,ref=1,val=0xC000,seg=1,type=lab
+sym\tid=1,name=\"frame_count\",addrsize=zeropage,scope=0,def=2,val=0x0,seg=0,type=lab
+sym\tid=2,name=\"VIA\",addrsize=absolute,scope=0,def=3,val=0x2800,type=equ
+sym\tid=3,name=\"draw\",addrsize=absolute,size=4,scope=0,def=4,val=0x8010,seg=2,type=lab
+sym\tid=4,name=\"draw_other\",addrsize=absolute,scope=0,def=5,val=0x8000,seg=3,type=lab
+sym\tid=5,name=\"reset\",addrsize=absolute,scope=1,def=6,val=0xC000,type=imp
+";
+
+    fn label(table: &SymbolTable, address: u16, bank: Option<u8>) -> Option<String> {
+        table.label(address, bank).map(|l| l.to_string())
+    }
+
+    #[test]
+    fn ld65_symbols() {
+        let table = SymbolTable::from_ld65_dbg(DBG).unwrap();
+        assert_eq!(table.symbols().len(), 5);
+        assert_eq!(table.address_of("reset"), Some((0xC000, Some(127))));
+        assert_eq!(table.address_of("frame_count"), Some((0x0000, None)));
+        assert_eq!(table.address_of("VIA"), Some((0x2800, None)));
+        assert_eq!(table.address_of("draw"), Some((0x8010, Some(0))));
+        assert_eq!(table.address_of("draw_other"), Some((0x8000, Some(1))));
+        assert_eq!(table.address_of("missing"), None);
+        assert!(table.symbols().iter().any(|s| s.name == "VIA" && s.is_equate));
+    }
+
+    #[test]
+    fn ld65_labels() {
+        let table = SymbolTable::from_ld65_dbg(DBG).unwrap();
+        assert_eq!(label(&table, 0xC003, Some(127)).as_deref(), Some("reset+$3"));
+        assert_eq!(label(&table, 0xC005, Some(127)), None);
+        assert_eq!(label(&table, 0x8012, Some(0)).as_deref(), Some("draw+$2"));
+        assert_eq!(label(&table, 0x8012, Some(1)).as_deref(), Some("draw_other+$12"));
+        assert_eq!(label(&table, 0x8014, Some(0)), None);
+        assert_eq!(label(&table, 0x2800, None).as_deref(), Some("VIA"));
+        assert_eq!(label(&table, 0x2801, None), None);
+        assert_eq!(label(&table, 0x0003, None).as_deref(), Some("frame_count+$3"));
+    }
+
+    #[test]
+    fn ld65_lines() {
+        let table = SymbolTable::from_ld65_dbg(DBG).unwrap();
+        assert_eq!(table.source_line(0xC000, Some(127)), Some(("main.s", 10)));
+        assert_eq!(table.source_line(0xC004, Some(127)), Some(("main.s", 11)));
+        assert_eq!(table.source_line(0xC005, Some(127)), None);
+        assert_eq!(table.source_line(0x8013, Some(0)), Some(("src/bank, data.s", 5)));
+        assert_eq!(table.source_line(0x8000, Some(1)), Some(("src/bank, data.s", 5)));
+        assert_eq!(table.source_line(0x8000, Some(0)), None);
+    }
+
+    fn dbg_error(text: &str) -> SymbolError {
+        SymbolTable::from_ld65_dbg(text).unwrap_err()
+    }
+
+    #[test]
+    fn ld65_errors() {
+        let error = |line, reason| SymbolError { line, reason };
+        assert_eq!(dbg_error("version\tmajor=2\nsym\tid=0,name=\"open"), error(2, "malformed record"));
+        assert_eq!(dbg_error("sym\tid=0,val"), error(1, "malformed record"));
+        assert_eq!(dbg_error("seg\tid=0,start=0xZZ"), error(1, "bad number"));
+        assert_eq!(dbg_error("seg\tstart=0"), error(1, "missing id"));
+        assert_eq!(dbg_error("file\tid=0"), error(1, "missing file name"));
+        assert_eq!(dbg_error("span\tid=0,start=0"), error(1, "missing span segment"));
+        assert_eq!(dbg_error("line\tid=0,file=0,line=1,span=1+x"), error(1, "bad span list"));
+        assert_eq!(dbg_error("sym\tid=0,val=0x10"), error(1, "missing symbol name"));
+        assert_eq!(dbg_error("line\tid=0,file=0,line=1,span=0"), error(1, "unknown file"));
+        assert_eq!(dbg_error("file\tid=0,name=\"a.s\"\nline\tid=0,file=0,line=1,span=0"), error(2, "unknown span"));
+        assert_eq!(dbg_error("file\tid=0,name=\"a.s\"\nspan\tid=0,seg=0,start=0,size=1\nline\tid=0,file=0,line=1,span=0"), error(3, "unknown segment"));
+
+        let overflow = "file\tid=0,name=\"a.s\"\nseg\tid=0,start=0xFFFFFFFF\nspan\tid=0,seg=0,start=1,size=1\nline\tid=0,file=0,line=1,span=0";
+        assert_eq!(dbg_error(overflow), error(4, "span start out of range"));
+        let overflow = "file\tid=0,name=\"a.s\"\nseg\tid=0,start=0xFFFFFFF0\nspan\tid=0,seg=0,start=0,size=0x20\nline\tid=0,file=0,line=1,span=0";
+        assert_eq!(dbg_error(overflow), error(4, "span end out of range"));
+        // a symbol past the end of its segment's output just has no bank
+        let table = SymbolTable::from_ld65_dbg("seg\tid=0,start=0xFFFFFFFF,oname=\"a\",ooffs=0xFFFFFFFF\nsym\tid=0,name=\"a\",val=0xFFFF0000,seg=0").unwrap();
+        assert_eq!(table.address_of("a"), Some((0x0000, None)));
+    }
+
+    #[test]
+    fn vice_labels() {
+        let text = "al C:C000 .reset\nal 00E000 .main\nal 7F8000 .bank_code\nbreak C:E000\n\nal 0010 frame_count\n";
+        let table = SymbolTable::from_vice_labels(text).unwrap();
+        assert_eq!(table.symbols().len(), 4);
+        assert_eq!(table.address_of("reset"), Some((0xC000, None)));
+        assert_eq!(table.address_of("main"), Some((0xE000, None)));
+        assert_eq!(table.address_of("bank_code"), Some((0x8000, Some(0x7F))));
+        assert_eq!(table.address_of("frame_count"), Some((0x0010, None)));
+        assert_eq!(label(&table, 0x8004, Some(0x7F)).as_deref(), Some("bank_code+$4"));
+        assert_eq!(label(&table, 0x8004, Some(0x00)), None);
+    }
+
+    #[test]
+    fn vice_label_errors() {
+        let error = |line, reason| Err(SymbolError { line, reason });
+        assert_eq!(SymbolTable::from_vice_labels("al").map(|_| ()), error(1, "missing address"));
+        assert_eq!(SymbolTable::from_vice_labels("al C:C000 .a\nal C:XYZ .b").map(|_| ()), error(2, "bad address"));
+        assert_eq!(SymbolTable::from_vice_labels("al C000").map(|_| ()), error(1, "missing name"));
+    }
+}
@@ -0,0 +1,44 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use crate::emulator::{Emulator, TimeDaemon};
+
+/// A clock that never moves, for tests that step the machine by hand.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct StoppedClock;
+
+impl TimeDaemon for StoppedClock {
+    fn get_now_ms(&self) -> f64 {
+        0.0
+    }
+}
+
+/// An 8K rom that counts loop iterations at $10, keeps a running 16-bit sum of reads of the
+/// first gamepad at $11 and logs them to $0200-$02FF, so its state depends on both time and
+/// input.
+pub fn counter_rom() -> Vec<u8> {
+    let mut rom = vec![0; 0x2000];
+    let code = [
+        0xA2, 0x00,             // ldx #0
+        0xE6, 0x10,             // loop: inc $10
+        0xAD, 0x08, 0x20,       // lda $2008
+        0x9D, 0x00, 0x02,       // sta $0200,x
+        0xE8,                   // inx
+        0x18,                   // clc
+        0x65, 0x11,             // adc $11
+        0x85, 0x11,             // sta $11
+        0x90, 0x02,             // bcc +2
+        0xE6, 0x12,             // inc $12
+        0x4C, 0x02, 0xE0,       // jmp loop
+        0x40,                   // rti
+    ];
+    rom[..code.len()].copy_from_slice(&code);
+    rom[0x1FFA..].copy_from_slice(&[0x17, 0xE0, 0x00, 0xE0, 0x17, 0xE0]);
+    rom
+}
+
+/// A machine with `counter_rom` loaded.
+pub fn emulator() -> Emulator<StoppedClock> {
+    let mut emulator = Emulator::init(StoppedClock, 44100.0);
+    emulator.load_rom(&counter_rom()).unwrap();
+    emulator
+}
@@ -0,0 +1,244 @@
+use alloc::boxed::Box;
+use alloc::collections::VecDeque;
+use core::fmt::{Display, Write};
+use gte_w65c02s::disasm::{self, Instruction};
+use gte_w65c02s::{State, W65C02S};
+use log::warn;
+use crate::debugger::CpuId;
+
+/// What a traced step of a cpu did.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TraceEvent {
+    /// Executed the instruction at `pc`.
+    Instruction,
+    /// Entered the NMI handler (vblank, on the main cpu) instead of executing an instruction.
+    Nmi,
+    /// Entered the IRQ handler (blitter done on the main cpu, sample timer on the ACP).
+    Irq,
+    /// Came out of reset and jumped through the reset vector.
+    Reset,
+}
+
+/// One traced step, with the registers as they were before it.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct TraceEntry {
+    pub cpu: CpuId,
+    /// Cycles this cpu has run since tracing started.
+    pub cycle: u64,
+    pub event: TraceEvent,
+    pub pc: u16,
+    /// The 2M cartridge bank mapped at `pc`, if it's in banked rom.
+    pub bank: Option<u8>,
+    /// The instruction at `pc`. For interrupt entries, the one the interrupt preempted.
+    pub instruction: Instruction,
+    pub a: u8,
+    pub x: u8,
+    pub y: u8,
+    pub s: u8,
+    pub p: u8,
+}
+
+/// Which columns a trace line has. The defaults produce the nestest/Nintendulator layout most
+/// 6502 trace diffing tools expect:
+///
+/// `C000  4C F5 C5  JMP $C5F5                       A:00 X:00 Y:00 P:24 SP:FD CYC:7`
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct TraceFormat {
+    /// Prefix lines with `CPU`/`ACP`, for traces of both cpus in one output.
+    pub cpu_tag: bool,
+    /// Prefix the pc with the mapped bank, as in `7E:8000`.
+    pub bank: bool,
+    /// Show operands as `symbol+$offset` where the symbol tables know them.
+    pub symbols: bool,
+    /// Show P as `NV-BDIZC` letters, uppercase for set flags, instead of hex.
+    pub flag_letters: bool,
+    pub cycles: bool,
+}
+
+impl Default for TraceFormat {
+    fn default() -> Self {
+        Self { cpu_tag: false, bank: false, symbols: false, flag_letters: false, cycles: true }
+    }
+}
+
+impl TraceEntry {
+    /// Describes what `cpu`'s next step will do, or `None` if it will just keep waiting.
+    pub(crate) fn capture(id: CpuId, cpu: &W65C02S, cycle: u64, bank: Option<u8>, peek: impl FnMut(u16) -> u8) -> Option<Self> {
+        let event = match cpu.get_state() {
+            State::Running if cpu.get_nmi_pending() => TraceEvent::Nmi,
+            State::Running if cpu.get_irq_pending() => TraceEvent::Irq,
+            State::Running => TraceEvent::Instruction,
+            State::HasBeenReset => TraceEvent::Reset,
+            State::AwaitingInterrupt | State::Stopped => return None,
+        };
+        let pc = cpu.get_pc();
+        Some(Self {
+            cpu: id,
+            cycle,
+            event,
+            pc,
+            bank,
+            instruction: disasm::decode(pc, peek),
+            a: cpu.get_a(),
+            x: cpu.get_x(),
+            y: cpu.get_y(),
+            s: cpu.get_s(),
+            p: cpu.get_p(),
+        })
+    }
+
+    /// Writes the entry as a single line, without the line break. `labels` names addresses
+    /// when `format.symbols` is set.
+    pub fn write_to<W: Write + ?Sized, S: Display>(&self, out: &mut W, format: &TraceFormat, labels: impl Fn(u16) -> Option<S>) -> core::fmt::Result {
+        if format.cpu_tag {
+            out.write_str(match self.cpu { CpuId::Main => "CPU ", CpuId::Acp => "ACP " })?;
+        }
+        if format.bank {
+            match self.bank {
+                Some(bank) => write!(out, "{:02X}:", bank)?,
+                None => out.write_str("--:")?,
+            }
+        }
+        write!(out, "{:04X}  ", self.pc)?;
+
+        // the disassembly column is padded, so render it on its own first
+        let mut text = heapless::String::<64>::new();
+        match self.event {
+            TraceEvent::Instruction => {
+                let bytes = [self.instruction.opcode, self.instruction.operand as u8, (self.instruction.operand >> 8) as u8];
+                let mut hex = heapless::String::<8>::new();
+                for (i, b) in bytes[..self.instruction.len() as usize].iter().enumerate() {
+                    if i > 0 { hex.push(' ').ok(); }
+                    write!(hex, "{:02X}", b)?;
+                }
+                write!(out, "{:<8}  ", hex)?;
+                // a symbol too long to fit just gets cut short
+                if format.symbols {
+                    let _ = self.instruction.write_to(&mut text, labels);
+                } else {
+                    let _ = self.instruction.write_to(&mut text, |_| None::<&str>);
+                }
+            }
+            TraceEvent::Nmi => { write!(out, "{:<8}  ", "")?; text.push_str("-- NMI --").ok(); }
+            TraceEvent::Irq => { write!(out, "{:<8}  ", "")?; text.push_str("-- IRQ --").ok(); }
+            TraceEvent::Reset => { write!(out, "{:<8}  ", "")?; text.push_str("-- RESET --").ok(); }
+        }
+        write!(out, "{:<32}", text)?;
+
+        write!(out, "A:{:02X} X:{:02X} Y:{:02X} P:", self.a, self.x, self.y)?;
+        if format.flag_letters {
+            for (bit, letter) in (0..8).rev().zip("NV-BDIZC".chars()) {
+                let set = self.p & (1 << bit) != 0;
+                out.write_char(if set { letter } else { letter.to_ascii_lowercase() })?;
+            }
+        } else {
+            write!(out, "{:02X}", self.p)?;
+        }
+        write!(out, " SP:{:02X}", self.s)?;
+        if format.cycles {
+            write!(out, " CYC:{}", self.cycle)?;
+        }
+        Ok(())
+    }
+}
+
+/// Records every step of the traced cpus into a bounded ring buffer, and optionally writes
+/// each one out as a text line as it happens.
+pub struct Tracer {
+    pub trace_main: bool,
+    pub trace_acp: bool,
+    pub format: TraceFormat,
+    capacity: usize,
+    entries: VecDeque<TraceEntry>,
+    output: Option<Box<dyn Write + Send>>,
+    pub(crate) cycles: [u64; 2],
+}
+
+impl core::fmt::Debug for Tracer {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Tracer")
+            .field("trace_main", &self.trace_main)
+            .field("trace_acp", &self.trace_acp)
+            .field("format", &self.format)
+            .field("capacity", &self.capacity)
+            .field("len", &self.entries.len())
+            .finish()
+    }
+}
+
+/// Clones keep the buffered entries but don't write to the original's output.
+impl Clone for Tracer {
+    fn clone(&self) -> Self {
+        Self {
+            trace_main: self.trace_main,
+            trace_acp: self.trace_acp,
+            format: self.format,
+            capacity: self.capacity,
+            entries: self.entries.clone(),
+            output: None,
+            cycles: self.cycles,
+        }
+    }
+}
+
+impl Tracer {
+    /// Traces the main cpu, keeping the last `capacity` entries.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            trace_main: true,
+            trace_acp: false,
+            format: TraceFormat::default(),
+            capacity,
+            entries: VecDeque::with_capacity(capacity.min(0x10000)),
+            output: None,
+            cycles: [0; 2],
+        }
+    }
+
+    /// Also writes every entry to `output`, one line each.
+    pub fn set_output(&mut self, output: Box<dyn Write + Send>) {
+        self.output = Some(output);
+    }
+
+    pub fn take_output(&mut self) -> Option<Box<dyn Write + Send>> {
+        self.output.take()
+    }
+
+    pub fn is_tracing(&self, cpu: CpuId) -> bool {
+        match cpu {
+            CpuId::Main => self.trace_main,
+            CpuId::Acp => self.trace_acp,
+        }
+    }
+
+    /// The buffered entries, oldest first.
+    pub fn entries(&self) -> impl Iterator<Item = &TraceEntry> {
+        self.entries.iter()
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    pub(crate) fn record<S: Display>(&mut self, entry: TraceEntry, labels: impl Fn(u16) -> Option<S>) {
+        if let Some(output) = &mut self.output {
+            let result = entry.write_to(output.as_mut(), &self.format, labels).and_then(|_| output.write_char('\n'));
+            if result.is_err() {
+                warn!("trace output failed, no longer writing trace lines");
+                self.output = None;
+            }
+        }
+
+        if self.capacity == 0 {
+            return;
+        }
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(entry);
+    }
+
+    pub(crate) fn advance(&mut self, cpu: CpuId, cycles: i32) {
+        self.cycles[cpu as usize] += cycles as u64;
+    }
+}
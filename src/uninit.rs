@@ -0,0 +1,74 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// What made a read of never written memory.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum UninitReader {
+    Cpu,
+    Acp,
+    /// The blitter fetching a source pixel.
+    Blitter,
+}
+
+/// The memory a never written byte is in.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum UninitMemory {
+    Ram { bank: u8 },
+    Vram { page: u8 },
+    Aram,
+}
+
+/// A read of a byte nothing wrote since power on, reported by the uninitialized memory
+/// detector.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct UninitRead {
+    pub reader: UninitReader,
+    /// The instruction that made the read: the ACP's for ACP reads, the main cpu's otherwise.
+    /// Blits run alongside the main cpu, so for them it's only where the main cpu was.
+    pub pc: u16,
+    /// The address read, in the reader's address space. For the blitter it's the offset into
+    /// the VRAM page.
+    pub address: u16,
+    pub memory: UninitMemory,
+    /// Offset of the byte into its RAM bank, VRAM page or audio RAM.
+    pub offset: usize,
+}
+
+/// A read collected on a bus, before the emulator knows which instruction made it.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) struct UninitHit {
+    pub reader: UninitReader,
+    pub address: u16,
+    pub memory: UninitMemory,
+    pub offset: usize,
+}
+
+impl UninitHit {
+    pub(crate) fn at(self, pc: u16) -> UninitRead {
+        UninitRead { reader: self.reader, pc, address: self.address, memory: self.memory, offset: self.offset }
+    }
+}
+
+/// One bit per byte of a memory, set once the byte has been written since power on.
+#[derive(Clone, Debug)]
+pub(crate) struct WrittenBits(Vec<u64>);
+
+impl WrittenBits {
+    pub(crate) fn new(bytes: usize) -> Self {
+        Self(vec![0; bytes.div_ceil(64)])
+    }
+
+    #[inline(always)]
+    pub(crate) fn set(&mut self, index: usize) {
+        self.0[index / 64] |= 1 << (index % 64);
+    }
+
+    pub(crate) fn set_all(&mut self) {
+        self.0.fill(!0);
+    }
+
+    #[inline(always)]
+    pub(crate) fn get(&self, index: usize) -> bool {
+        self.0[index / 64] & 1 << (index % 64) != 0
+    }
+}
@@ -0,0 +1,94 @@
+use alloc::boxed::Box;
+use crate::emulator::{CYCLES_PER_FRAME, HEIGHT, WIDTH};
+
+/// Scanlines in a frame, vblank to vblank.
+pub const SCANLINES: u16 = 262;
+/// Scanlines each framebuffer row is shown on.
+pub const LINES_PER_ROW: u16 = 2;
+/// Scanlines of vertical blanking after vblank, before the first framebuffer row.
+pub const BLANK_LINES: u16 = SCANLINES - HEIGHT as u16 * LINES_PER_ROW;
+/// CPU cycles at the start of each scanline before its first pixel. A pixel lasts one and a
+/// half cycles after that, so the 128 of them fill the rest of the line.
+pub const HBLANK_CYCLES: u16 = 35;
+
+/// Where the video beam is, counted from the start of the frame at vblank.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct BeamPosition {
+    pub scanline: u16,
+    /// CPU cycles since the start of the scanline.
+    pub cycle: u16,
+}
+
+impl BeamPosition {
+    /// The position `frame_cycle` cycles after vblank.
+    pub fn at(frame_cycle: u32) -> Self {
+        let frame_cycle = frame_cycle.min(CYCLES_PER_FRAME as u32 - 1);
+        let scanline = (frame_cycle * SCANLINES as u32 / CYCLES_PER_FRAME as u32) as u16;
+        Self { scanline, cycle: (frame_cycle - line_start(scanline)) as u16 }
+    }
+
+    /// The framebuffer row being drawn, or `None` during vertical blanking.
+    pub fn row(&self) -> Option<u8> {
+        self.scanline.checked_sub(BLANK_LINES).map(|line| (line / LINES_PER_ROW) as u8)
+    }
+
+    /// The framebuffer column being drawn, or `None` during blanking.
+    pub fn column(&self) -> Option<u8> {
+        self.row()?;
+        let column = self.cycle.checked_sub(HBLANK_CYCLES)? * 2 / 3;
+        (column < WIDTH as u16).then_some(column as u8)
+    }
+}
+
+/// The first cycle of `scanline`, counted from vblank. The frame doesn't divide evenly into
+/// scanlines, so some are a cycle longer than others.
+fn line_start(scanline: u16) -> u32 {
+    (scanline as u32 * CYCLES_PER_FRAME as u32).div_ceil(SCANLINES as u32)
+}
+
+/// The cycle the beam starts drawing framebuffer row `row` at.
+fn row_start(row: usize) -> u32 {
+    line_start(BLANK_LINES + row as u16 * LINES_PER_ROW) + HBLANK_CYCLES as u32
+}
+
+/// The picture on screen. Each row is latched from whichever framebuffer is displayed at the
+/// moment the beam starts drawing it, so flipping framebuffers mid-frame tears like on
+/// hardware, and rows the beam hasn't reached yet still show the previous frame.
+#[derive(Clone, Debug)]
+pub(crate) struct VideoOutput {
+    pub image: Box<[u8; (WIDTH * HEIGHT) as usize]>,
+    rows_latched: usize,
+}
+
+impl VideoOutput {
+    pub fn new(displayed: &[u8]) -> Self {
+        let mut image = Box::new([0; (WIDTH * HEIGHT) as usize]);
+        image.copy_from_slice(displayed);
+        Self { image, rows_latched: 0 }
+    }
+
+    /// Whether the beam has started drawing a row not latched yet by `frame_cycle`.
+    #[inline(always)]
+    pub fn due(&self, frame_cycle: u32) -> bool {
+        self.rows_latched < HEIGHT as usize && row_start(self.rows_latched) <= frame_cycle
+    }
+
+    /// Latches every row the beam has started drawing by `frame_cycle`.
+    pub fn catch_up(&mut self, frame_cycle: u32, displayed: &[u8]) {
+        while self.due(frame_cycle) {
+            let row = self.rows_latched * WIDTH as usize..(self.rows_latched + 1) * WIDTH as usize;
+            self.image[row.clone()].copy_from_slice(&displayed[row]);
+            self.rows_latched += 1;
+        }
+    }
+
+    /// Starts the next frame, once the last one's rows have all been latched.
+    pub fn vblank(&mut self) {
+        self.rows_latched = 0;
+    }
+
+    /// Picks up at `frame_cycle` with the image as it is, as when restoring a save state.
+    pub fn resume_at(&mut self, frame_cycle: u32) {
+        self.rows_latched = (0..HEIGHT as usize).take_while(|&row| row_start(row) <= frame_cycle).count();
+    }
+}